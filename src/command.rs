@@ -1,32 +1,924 @@
+use std::path::PathBuf;
+
+use crate::{error::VaultError, fold::FoldCommand, table::TableEdit};
+
+#[derive(Debug, Clone)]
 pub enum Command {
     Quit,
-    Save,
+    /// Write the buffer, or a copy of it to another path
+    Save(Option<PathBuf>),
     SaveQuit,
+    Edit(PathBuf),
     NewNote,
+    /// Create a note from the given template, the folder's default one, or a picked one
+    NewFromTemplate(Option<PathBuf>),
     FollowLink,
-    InsertTemplate,
+    /// Read the buffer rendered, following its links
+    Preview,
+    /// Open the note embedded on the cursor line as `![[note#section]]`
+    OpenEmbed,
+    /// Open or close folds
+    Fold(FoldCommand),
+    /// Change the table at the cursor, or turn the lines of the range into one
+    Table(TableEdit),
+    /// Wrap the lines of the range in a callout of a type, with an optional title
+    Callout {
+        kind: String,
+        title: String,
+    },
+    /// Insert the given template, or pick one
+    InsertTemplate(Option<PathBuf>),
     NewTab,
     FocusTab(u8),
+    /// Counted from 1, as in `:tabn 3`
+    SelectTab(usize),
     NextBuffer,
     PreviousBuffer,
     SearchNote,
+    /// Move the cursor to the end of the range, as `:10` does
+    GoToLine,
+    Delete,
+    Substitute(Substitute),
+    /// Run a command on every line matching (or with `!`, not matching) a pattern
+    Global {
+        pattern: String,
+        command: String,
+    },
+    /// Replace a pattern in every note of the vault, after a preview
+    Replace(Substitute),
+    /// Put back the notes changed by the last `:replace`
+    UndoReplace,
+    Undo,
+    Redo,
+    Palette,
+    /// The arguments of `:set`
+    Set(String),
+    /// Check or uncheck the task on the cursor line
+    ToggleTask,
+    /// List the open tasks of the vault that match a filter
+    Tasks(String),
+    /// List the overdue, today's and upcoming tasks of the vault that match a filter
+    Agenda(String),
+    /// Insert the date a phrase like `next friday` means, or with `!` a link to its daily note
+    Date {
+        phrase: String,
+        link: bool,
+    },
     None,
 }
 
-impl Command {
-    pub fn str_to_command(string: &str) -> Self {
-        match string {
-            "quit" | "q" => Command::Quit,
-            "write" | "w" | "save" => Command::Save,
-            "wq" => Command::SaveQuit,
-            "new note" | "nn" => Command::NewNote,
-            "follow" | "follow link" | "fl" => Command::FollowLink,
-            "insert template" | "itm" => Command::InsertTemplate,
-            "new tab" | "nt" => Command::NewTab,
-            "next buffer" | "nb" => Command::NextBuffer,
-            "previous buffer" | "prev buffer" | "pb" => Command::PreviousBuffer,
-            "search note" | "search" | "sn" => Command::SearchNote,
-            _ => Command::None,
+#[derive(Debug, Clone)]
+pub struct Substitute {
+    /// An empty pattern reuses the last search
+    pub pattern: String,
+    /// Already translated from Vim's `&` and `\1` to the regex crate's `${0}` and `${1}`
+    pub replacement: String,
+    pub all: bool,
+    pub ignore_case: bool,
+}
+
+/// A line number as written in a range, before it is resolved against a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    Line(usize),
+    Current,
+    Last,
+    Mark(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSpec {
+    address: Address,
+    offset: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    start: LineSpec,
+    end: LineSpec,
+}
+
+impl Range {
+    pub fn current_line() -> Self {
+        let line = LineSpec {
+            address: Address::Current,
+            offset: 0,
+        };
+        Self {
+            start: line,
+            end: line,
         }
     }
+
+    pub fn whole_file() -> Self {
+        Self {
+            start: LineSpec {
+                address: Address::Line(1),
+                offset: 0,
+            },
+            end: LineSpec {
+                address: Address::Last,
+                offset: 0,
+            },
+        }
+    }
+
+    /// Turn the range into 0 based rows of a buffer with `len` lines.
+    /// `marks` are the first and last rows of the last visual selection
+    pub fn resolve(
+        &self,
+        cursor: usize,
+        len: usize,
+        marks: Option<(usize, usize)>,
+    ) -> Result<(usize, usize), VaultError> {
+        let resolve = |spec: LineSpec| -> Result<usize, VaultError> {
+            let row = match spec.address {
+                Address::Line(line) => line as i64 - 1,
+                Address::Current => cursor as i64,
+                Address::Last => len as i64 - 1,
+                Address::Mark('<') => marks.ok_or(no_marks())?.0 as i64,
+                Address::Mark('>') => marks.ok_or(no_marks())?.1 as i64,
+                Address::Mark(mark) => {
+                    return Err(VaultError::Command(format!("Mark not set: {}", mark)));
+                }
+            } + spec.offset;
+
+            if row < 0 || row >= len as i64 {
+                return Err(VaultError::Command("Invalid range".to_string()));
+            }
+            Ok(row as usize)
+        };
+
+        let start = resolve(self.start)?;
+        let end = resolve(self.end)?;
+        if start > end {
+            return Err(VaultError::Command("Backwards range given".to_string()));
+        }
+        Ok((start, end))
+    }
+}
+
+fn no_marks() -> VaultError {
+    VaultError::Command("Mark not set: no visual selection yet".to_string())
+}
+
+/// A parsed command line: `[range]name[!] [arguments]`
+#[derive(Debug, Clone)]
+pub struct ExCommand {
+    pub range: Option<Range>,
+    pub bang: bool,
+    pub command: Command,
+}
+
+/// What a command takes after its name, which decides how Tab completes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Argument {
+    None,
+    Path,
+    Template,
+    Number,
+    Pattern,
+    Option,
+    Date,
+    Callout,
+    Table,
+}
+
+/// A command as listed by the command palette and completed by Tab
+pub struct CommandInfo {
+    /// The first name is the one shown, the others are aliases
+    pub names: &'static [&'static str],
+    pub usage: &'static str,
+    pub description: &'static str,
+    /// The key bound to the command in normal mode, empty when there is none
+    pub keys: &'static str,
+    pub argument: Argument,
+    /// The palette opens the command line for these instead of running them
+    pub required: bool,
+}
+
+const fn info(
+    names: &'static [&'static str],
+    usage: &'static str,
+    description: &'static str,
+    keys: &'static str,
+    argument: Argument,
+    required: bool,
+) -> CommandInfo {
+    CommandInfo {
+        names,
+        usage,
+        description,
+        keys,
+        argument,
+        required,
+    }
+}
+
+pub const COMMANDS: [CommandInfo; 34] = [
+    info(
+        &["quit", "q"],
+        "quit",
+        "Close the current tab",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["write", "w", "save"],
+        "write [PATH]",
+        "Save the note, or a copy of it to PATH",
+        "",
+        Argument::Path,
+        false,
+    ),
+    info(
+        &["wq", "x"],
+        "wq",
+        "Save the note and close the tab",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["edit", "e"],
+        "edit PATH",
+        "Open a note, creating it when it does not exist",
+        "",
+        Argument::Path,
+        true,
+    ),
+    info(
+        &["new note", "nn"],
+        "new note",
+        "Create a note named after the date and a title",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["new-from-template", "nft"],
+        "new-from-template [TEMPLATE]",
+        "Create a note from a template, asking for its title",
+        "",
+        Argument::Template,
+        false,
+    ),
+    info(
+        &["follow link", "follow", "fl"],
+        "follow link",
+        "Open the wikilink under the cursor",
+        "Enter",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["insert template", "itm"],
+        "insert template [TEMPLATE]",
+        "Insert a template at the cursor, picking one when none is given",
+        "",
+        Argument::Template,
+        false,
+    ),
+    info(
+        &["new tab", "nt"],
+        "new tab",
+        "Open a new tab",
+        "Ctrl-t",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["tabnext", "tabn"],
+        "tabnext [N]",
+        "Go to the next tab, or to tab N",
+        "Right",
+        Argument::Number,
+        false,
+    ),
+    info(
+        &["tabprevious", "tabp", "tabN"],
+        "tabprevious",
+        "Go to the previous tab",
+        "Left",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["next buffer", "nb"],
+        "next buffer",
+        "Show the next note opened in this tab",
+        "Up",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["previous buffer", "prev buffer", "pb"],
+        "previous buffer",
+        "Show the previous note opened in this tab",
+        "Down",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["search note", "search", "sn"],
+        "search note",
+        "Fuzzy find a note and open it",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(&[], "N", "Go to line N", "", Argument::None, true),
+    info(
+        &["delete", "d"],
+        "[range]delete",
+        "Delete the lines of the range",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["fold"],
+        "fold",
+        "Open or close the fold at the cursor, by heading, list item or code block",
+        "za",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["foldopen", "foldo"],
+        "foldopen[!]",
+        "Open the fold at the cursor, or with ! every fold",
+        "zo zR",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["foldclose", "foldc"],
+        "foldclose[!]",
+        "Close the fold at the cursor, or with ! every fold",
+        "zc zM",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["table"],
+        "[range]table ACTION",
+        "Edit the table at the cursor: align, row-above, row-below, delete-row, column-left, column-right, delete-column, sort [N] [desc], to-csv or to-tsv, or make one of the range with from-csv or from-tsv",
+        "Tab Shift-Tab",
+        Argument::Table,
+        true,
+    ),
+    info(
+        &["callout"],
+        "[range]callout TYPE [TITLE]",
+        "Wrap the lines of the range, like the visual selection, in a callout",
+        "",
+        Argument::Callout,
+        true,
+    ),
+    info(
+        &["substitute", "s"],
+        "[range]s/PATTERN/REPLACEMENT/[giI]",
+        "Replace matches of a pattern in the range",
+        "",
+        Argument::Pattern,
+        true,
+    ),
+    info(
+        &["global", "g"],
+        "[range]g/PATTERN/COMMAND",
+        "Run :d or :s on every line matching a pattern",
+        "",
+        Argument::Pattern,
+        true,
+    ),
+    info(
+        &["vglobal", "v"],
+        "[range]v/PATTERN/COMMAND",
+        "Run :d or :s on every line not matching a pattern",
+        "",
+        Argument::Pattern,
+        true,
+    ),
+    info(
+        &["replace"],
+        "replace/PATTERN/REPLACEMENT/[giI]",
+        "Replace a pattern in every note, previewing each change",
+        "",
+        Argument::Pattern,
+        true,
+    ),
+    info(
+        &["undoreplace"],
+        "undoreplace",
+        "Put back the notes changed by the last :replace",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["set", "se"],
+        "set [OPTION]...",
+        "Change options like ignorecase, or show them all",
+        "",
+        Argument::Option,
+        false,
+    ),
+    info(
+        &["toggle task", "tt"],
+        "toggle task",
+        "Check or uncheck the task on the cursor line",
+        "gx Ctrl-Space",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["tasks"],
+        "tasks [FILTER]",
+        "List the open tasks of every note, filtered by #tag, due:DATE or text",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["agenda"],
+        "agenda [FILTER]",
+        "List the overdue, today's and upcoming tasks, filtered by #tag or text",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["date"],
+        "date[!] PHRASE",
+        "Insert a date like today or next friday, or with ! a link to its daily note",
+        "@PHRASE Tab",
+        Argument::Date,
+        true,
+    ),
+    info(
+        &["preview"],
+        "preview",
+        "Read the note rendered, with Tab to select a link and Enter to follow it",
+        "Ctrl-e",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["embed"],
+        "embed",
+        "Open the note embedded on the cursor line, at its section",
+        "",
+        Argument::None,
+        false,
+    ),
+    info(
+        &["palette"],
+        "palette",
+        "Fuzzy find a command and run it",
+        "Ctrl-p",
+        Argument::None,
+        false,
+    ),
+];
+
+/// What Tab completes in a command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    Command,
+    Argument(Argument),
+}
+
+/// The byte offset of the word under completion at the end of `input`, and what it is
+pub fn completion_target(input: &str) -> (usize, Completion) {
+    let trimmed = input.trim_start_matches([' ', ':']);
+    let rest = match parse_range(trimmed) {
+        Ok((_, rest)) => rest.trim_start(),
+        Err(_) => trimmed,
+    };
+    let start = input.len() - rest.len();
+
+    let still_typing_name = COMMANDS
+        .iter()
+        .flat_map(|command| command.names)
+        .any(|name| name.starts_with(rest));
+    if still_typing_name {
+        return (start, Completion::Command);
+    }
+
+    let name_len = command_name_len(rest);
+    let argument = COMMANDS
+        .iter()
+        .find(|command| command.names.contains(&&rest[..name_len]))
+        .map_or(Argument::None, |command| command.argument);
+    let word = match argument {
+        // Paths may contain spaces, so they are completed as a whole
+        Argument::Path | Argument::Template => {
+            rest.len() - rest[name_len..].trim_start_matches(['!', ' ']).len()
+        }
+        _ => rest.rfind(' ').map_or(rest.len(), |space| space + 1),
+    };
+
+    (start + word, Completion::Argument(argument))
+}
+
+/// The length of the command name at the start of `input`, which may contain spaces or dashes
+fn command_name_len(input: &str) -> usize {
+    COMMANDS
+        .iter()
+        .flat_map(|command| command.names)
+        .filter(|name| {
+            name.contains([' ', '-'])
+                && input.starts_with(*name)
+                && matches!(input[name.len()..].chars().next(), None | Some(' ' | '!'))
+        })
+        .map(|name| name.len())
+        .max()
+        .unwrap_or_else(|| {
+            input
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(input.len())
+        })
+}
+
+impl ExCommand {
+    pub fn parse(input: &str) -> Result<Self, VaultError> {
+        let input = input.trim_start_matches([' ', ':']);
+        let (range, rest) = parse_range(input)?;
+        let rest = rest.trim_start();
+
+        let (name, rest) = rest.split_at(command_name_len(rest));
+
+        let (bang, args) = match rest.strip_prefix('!') {
+            Some(args) => (true, args),
+            None => (false, rest),
+        };
+        // The delimiter of `:s` and `:g` may be a space, so keep their arguments intact
+        let args = if matches!(
+            name,
+            "s" | "substitute" | "g" | "global" | "v" | "vglobal" | "replace"
+        ) {
+            args
+        } else {
+            args.trim()
+        };
+
+        let no_args = |command: Command| -> Result<Command, VaultError> {
+            if !args.is_empty() {
+                return Err(VaultError::Command(format!(
+                    "Trailing characters: {}",
+                    args
+                )));
+            }
+            Ok(command)
+        };
+        let path = || -> Option<PathBuf> { (!args.is_empty()).then(|| PathBuf::from(args)) };
+
+        let command = match name {
+            "" if range.is_some() => no_args(Command::GoToLine)?,
+            "" => no_args(Command::None)?,
+            "quit" | "q" => no_args(Command::Quit)?,
+            "write" | "w" | "save" => Command::Save(path()),
+            "wq" | "x" => no_args(Command::SaveQuit)?,
+            "edit" | "e" => {
+                Command::Edit(path().ok_or(VaultError::Command("Argument required".to_string()))?)
+            }
+            "new note" | "nn" => no_args(Command::NewNote)?,
+            "new-from-template" | "nft" => Command::NewFromTemplate(path()),
+            "follow" | "follow link" | "fl" => no_args(Command::FollowLink)?,
+            "preview" => no_args(Command::Preview)?,
+            "embed" => no_args(Command::OpenEmbed)?,
+            "insert template" | "itm" => Command::InsertTemplate(path()),
+            "new tab" | "nt" => no_args(Command::NewTab)?,
+            "tabnext" | "tabn" => match args.parse::<usize>() {
+                Ok(tab) => Command::SelectTab(tab),
+                Err(_) => no_args(Command::FocusTab(1))?,
+            },
+            "tabprevious" | "tabp" | "tabN" => no_args(Command::FocusTab(0))?,
+            "next buffer" | "nb" => no_args(Command::NextBuffer)?,
+            "previous buffer" | "prev buffer" | "pb" => no_args(Command::PreviousBuffer)?,
+            "search note" | "search" | "sn" => no_args(Command::SearchNote)?,
+            "replace" => Command::Replace(parse_substitute(args)?),
+            "undoreplace" => no_args(Command::UndoReplace)?,
+            "palette" => no_args(Command::Palette)?,
+            "set" | "se" => Command::Set(args.to_string()),
+            "toggle task" | "tt" => no_args(Command::ToggleTask)?,
+            "tasks" => Command::Tasks(args.to_string()),
+            "agenda" => Command::Agenda(args.to_string()),
+            "date" if args.is_empty() => {
+                return Err(VaultError::Command("Argument required".to_string()));
+            }
+            "date" => Command::Date {
+                phrase: args.to_string(),
+                link: bang,
+            },
+            "delete" | "d" => no_args(Command::Delete)?,
+            "fold" => no_args(Command::Fold(FoldCommand::Toggle))?,
+            "foldopen" | "foldo" if bang => no_args(Command::Fold(FoldCommand::OpenAll))?,
+            "foldopen" | "foldo" => no_args(Command::Fold(FoldCommand::Open))?,
+            "foldclose" | "foldc" if bang => no_args(Command::Fold(FoldCommand::CloseAll))?,
+            "foldclose" | "foldc" => no_args(Command::Fold(FoldCommand::Close))?,
+            "table" => Command::Table(TableEdit::parse(args).map_err(VaultError::Command)?),
+            "callout" if args.is_empty() => {
+                return Err(VaultError::Command("Argument required".to_string()));
+            }
+            "callout" => {
+                let (kind, title) = args.split_once(' ').unwrap_or((args, ""));
+                Command::Callout {
+                    kind: kind.to_lowercase(),
+                    title: title.trim().to_string(),
+                }
+            }
+            "substitute" | "s" => Command::Substitute(parse_substitute(args)?),
+            "global" | "g" | "vglobal" | "v" => {
+                let (pattern, command) = parse_global(args)?;
+                Command::Global { pattern, command }
+            }
+            _ => {
+                return Err(VaultError::Command(format!(
+                    "Not an editor command: {}",
+                    input
+                )));
+            }
+        };
+
+        Ok(Self {
+            range,
+            // `:v` is `:g!`
+            bang: bang || matches!(name, "v" | "vglobal"),
+            command,
+        })
+    }
+}
+
+fn parse_range(input: &str) -> Result<(Option<Range>, &str), VaultError> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(Range::whole_file()), rest));
+    }
+
+    let Some((start, rest)) = parse_line_spec(input)? else {
+        return Ok((None, input));
+    };
+
+    if let Some(rest) = rest.strip_prefix([',', ';']) {
+        let Some((end, rest)) = parse_line_spec(rest)? else {
+            return Err(VaultError::Command("Invalid range".to_string()));
+        };
+        return Ok((Some(Range { start, end }), rest));
+    }
+
+    Ok((Some(Range { start, end: start }), rest))
+}
+
+fn parse_line_spec(input: &str) -> Result<Option<(LineSpec, &str)>, VaultError> {
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+
+    let (address, mut rest) = if digits > 0 {
+        let line = input[..digits]
+            .parse()
+            .map_err(|_| VaultError::Command("Invalid range".to_string()))?;
+        (Some(Address::Line(line)), &input[digits..])
+    } else if let Some(rest) = input.strip_prefix('.') {
+        (Some(Address::Current), rest)
+    } else if let Some(rest) = input.strip_prefix('$') {
+        (Some(Address::Last), rest)
+    } else if let Some(rest) = input.strip_prefix('\'') {
+        let mark = rest
+            .chars()
+            .next()
+            .ok_or(VaultError::Command("Invalid range".to_string()))?;
+        (Some(Address::Mark(mark)), &rest[mark.len_utf8()..])
+    } else {
+        (None, input)
+    };
+
+    // Offsets like `.+2` or `$-1`, where a bare `+3` is relative to the current line
+    let mut offset = 0;
+    while let Some(sign) = rest.chars().next().filter(|c| matches!(c, '+' | '-')) {
+        let number = &rest[1..];
+        let digits = number
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(number.len());
+        let value: i64 = if digits == 0 {
+            1
+        } else {
+            number[..digits]
+                .parse()
+                .map_err(|_| VaultError::Command("Invalid range".to_string()))?
+        };
+        offset += if sign == '+' { value } else { -value };
+        rest = &number[digits..];
+    }
+
+    let address = match address {
+        Some(address) => address,
+        None if rest.len() != input.len() => Address::Current,
+        None => return Ok(None),
+    };
+
+    Ok(Some((LineSpec { address, offset }, rest)))
+}
+
+/// Split `/a/b/c` on its first character, which any later unescaped occurrence of ends a part at
+fn split_delimited(input: &str, parts: usize) -> Result<Vec<String>, VaultError> {
+    let mut chars = input.chars();
+    let delimiter = chars
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !matches!(c, '\\' | '"' | '|'))
+        .ok_or(VaultError::Command(
+            "Regular expressions can't be delimited by letters".to_string(),
+        ))?;
+
+    let mut split = vec![String::new()];
+    while let Some(c) = chars.next() {
+        if split.len() == parts {
+            // The last part takes the rest of the line as is
+            split.last_mut().unwrap().push(c);
+            continue;
+        }
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => split.last_mut().unwrap().push(next),
+                Some(next) => {
+                    split.last_mut().unwrap().push('\\');
+                    split.last_mut().unwrap().push(next);
+                }
+                None => split.last_mut().unwrap().push('\\'),
+            },
+            c if c == delimiter => split.push(String::new()),
+            c => split.last_mut().unwrap().push(c),
+        }
+    }
+
+    split.resize(parts, String::new());
+    Ok(split)
+}
+
+fn parse_substitute(args: &str) -> Result<Substitute, VaultError> {
+    let parts = split_delimited(args, 3)?;
+    let flags = parts[2].trim();
+
+    if let Some(flag) = flags.chars().find(|c| !matches!(c, 'g' | 'i' | 'I')) {
+        return Err(VaultError::Command(format!("Invalid flag: {}", flag)));
+    }
+
+    Ok(Substitute {
+        pattern: parts[0].clone(),
+        replacement: vim_replacement(&parts[1]),
+        all: flags.contains('g'),
+        ignore_case: flags.contains('i') && !flags.contains('I'),
+    })
+}
+
+fn parse_global(args: &str) -> Result<(String, String), VaultError> {
+    let parts = split_delimited(args, 2)?;
+    if parts[0].is_empty() {
+        return Err(VaultError::Command(
+            "Regular expression missing from :global".to_string(),
+        ));
+    }
+    Ok((parts[0].clone(), parts[1].trim().to_string()))
+}
+
+/// Translate Vim's replacement syntax to the one of the regex crate
+fn vim_replacement(replacement: &str) -> String {
+    let mut translated = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => translated += &format!("${{{}}}", digit),
+                Some('n' | 'r') => translated.push('\n'),
+                Some('t') => translated.push('\t'),
+                Some('$') => translated += "$$",
+                Some(other) => translated.push(other),
+                None => translated.push('\\'),
+            },
+            '&' => translated += "${0}",
+            '$' => translated += "$$",
+            c => translated.push(c),
+        }
+    }
+    translated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rows the range of a command line covers in a buffer of 10 lines with the cursor on
+    /// row 4 and the last visual selection on rows 2 to 6
+    fn rows(input: &str) -> Option<(usize, usize)> {
+        let range = ExCommand::parse(input).ok()?.range?;
+        range.resolve(4, 10, Some((2, 6))).ok()
+    }
+
+    fn substitute(input: &str) -> Substitute {
+        match ExCommand::parse(input).unwrap().command {
+            Command::Substitute(substitute) => substitute,
+            command => panic!("Not a substitution: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(rows("3d"), Some((2, 2)));
+        assert_eq!(rows(":2,5d"), Some((1, 4)));
+        assert_eq!(rows("%d"), Some((0, 9)));
+        assert_eq!(rows(".,$d"), Some((4, 9)));
+        assert_eq!(rows("2;4"), Some((1, 3)));
+        assert_eq!(rows("d"), None);
+        // Offsets count from the address, or from the cursor line without one
+        assert_eq!(rows(".+2"), Some((6, 6)));
+        assert_eq!(rows("$-1"), Some((8, 8)));
+        assert_eq!(rows("+,++"), Some((5, 6)));
+        assert_eq!(rows("-3"), Some((1, 1)));
+        assert_eq!(rows("3+2-1"), Some((3, 3)));
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        assert_eq!(rows("11"), None);
+        assert_eq!(rows("0"), None);
+        assert_eq!(rows("5,2d"), None);
+        assert!(ExCommand::parse("3,d").is_err());
+        assert!(ExCommand::parse("'").is_err());
+    }
+
+    #[test]
+    fn marks() {
+        assert_eq!(rows("'<,'>d"), Some((2, 6)));
+        assert_eq!(rows("'<+1,'>-1s/a/b/"), Some((3, 5)));
+        let range = ExCommand::parse("'<,'>d").unwrap().range.unwrap();
+        assert!(range.resolve(4, 10, None).is_err());
+        assert!(
+            ExCommand::parse("'a")
+                .unwrap()
+                .range
+                .unwrap()
+                .resolve(4, 10, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn commands() {
+        assert!(matches!(
+            ExCommand::parse("12").unwrap().command,
+            Command::GoToLine
+        ));
+        assert!(matches!(
+            ExCommand::parse("").unwrap().command,
+            Command::None
+        ));
+        assert!(matches!(
+            ExCommand::parse("w").unwrap().command,
+            Command::Save(None)
+        ));
+        assert!(ExCommand::parse("q now").is_err());
+        assert!(ExCommand::parse("frobnicate").is_err());
+
+        let global = ExCommand::parse("v/todo/d").unwrap();
+        assert!(global.bang);
+        assert!(matches!(
+            global.command,
+            Command::Global { pattern, command } if pattern == "todo" && command == "d"
+        ));
+        assert!(ExCommand::parse("g//d").is_err());
+    }
+
+    #[test]
+    fn delimiters() {
+        let escaped = substitute(r"s/a\/b/c\/d/g");
+        assert_eq!(escaped.pattern, "a/b");
+        assert_eq!(escaped.replacement, "c/d");
+        assert!(escaped.all);
+
+        // Other escapes are kept for the regex
+        assert_eq!(substitute(r"s#\d+#n#").pattern, r"\d+");
+        assert_eq!(substitute(r"s#a/b#c#").pattern, "a/b");
+        assert_eq!(substitute("s a b ").pattern, "a");
+        assert_eq!(substitute("s/a").replacement, "");
+
+        let flags = substitute("s/a/b/gi");
+        assert!(flags.all && flags.ignore_case);
+        assert!(!substitute("s/a/b/iI").ignore_case);
+        assert!(ExCommand::parse("s/a/b/x").is_err());
+        assert!(ExCommand::parse("sxaxbx").is_err());
+    }
+
+    #[test]
+    fn replacements() {
+        assert_eq!(vim_replacement("<&>"), "<${0}>");
+        assert_eq!(vim_replacement(r"\2-\1"), "${2}-${1}");
+        assert_eq!(vim_replacement(r"\0"), "${0}");
+        assert_eq!(vim_replacement(r"\&"), "&");
+        assert_eq!(vim_replacement(r"a\nb\tc"), "a\nb\tc");
+        assert_eq!(vim_replacement("$5"), "$$5");
+        assert_eq!(vim_replacement(r"\\"), r"\");
+        assert_eq!(vim_replacement("end\\"), "end\\");
+    }
 }
@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+
+/// The template variables that are not frontmatter fields
+const VARIABLES: [&str; 5] = ["title", "date", "time", "cursor", "prompt:"];
+
+/// What the word before the cursor completes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// The name of a note in `[[name`
+    Note,
+    /// A heading of a note in `[[note#heading`, of the note being edited when the name is
+    /// left out
+    Heading(String),
+    /// The shown text of a link in `[[note|alias`
+    Alias(String),
+    /// A tag, `#` included
+    Tag,
+    /// A variable of a template in `{{variable`
+    Variable,
+}
+
+/// The word before the cursor that a completion replaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: Kind,
+    /// The column the word starts at
+    pub start: usize,
+    pub typed: String,
+}
+
+/// A completion, what it inserts and what the popup shows of it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Candidate {
+    pub text: String,
+    pub label: String,
+    /// Where it comes from, shown next to it
+    pub source: &'static str,
+}
+
+impl Candidate {
+    pub fn new(text: impl Into<String>, source: &'static str) -> Self {
+        let text = text.into();
+        Self {
+            label: text.clone(),
+            text,
+            source,
+        }
+    }
+}
+
+/// The word being completed at a column of a line, if the cursor is in something that
+/// completes: a wikilink, a template variable or a tag
+pub fn token(line: &str, col: usize) -> Option<Token> {
+    let before: String = line.chars().take(col).collect();
+    let token = |kind, typed: &str| {
+        Some(Token {
+            kind,
+            start: col - typed.chars().count(),
+            typed: typed.to_string(),
+        })
+    };
+
+    if let Some(open) = before.rfind("[[")
+        && !before[open..].contains("]]")
+    {
+        let inner = &before[open + 2..];
+        if let Some((link, alias)) = inner.split_once('|') {
+            let note = link.split('#').next().unwrap_or_default();
+            return token(Kind::Alias(note.to_string()), alias);
+        }
+        if let Some((note, heading)) = inner.split_once('#') {
+            return token(Kind::Heading(note.to_string()), heading);
+        }
+        return token(Kind::Note, inner);
+    }
+
+    if let Some(open) = before.rfind("{{")
+        && !before[open..].contains("}}")
+    {
+        return token(Kind::Variable, before[open + 2..].trim_start());
+    }
+
+    let word = &before[before
+        .rfind(char::is_whitespace)
+        .map_or(0, |space| space + 1)..];
+    let name = word.strip_prefix('#')?;
+    if name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+    {
+        return token(Kind::Tag, word);
+    }
+    None
+}
+
+/// Whether what was just typed before a column opens the completion popup: `[[`, `{{`,
+/// the `#` or `|` of a wikilink or the first letter of a tag. A `#` alone may start a
+/// heading
+pub fn opens(line: &str, col: usize) -> bool {
+    let before: String = line.chars().take(col).collect();
+    if before.ends_with("[[") || before.ends_with("{{") {
+        return true;
+    }
+    token(line, col).is_some_and(|token| match token.kind {
+        Kind::Heading(_) | Kind::Alias(_) => token.typed.is_empty(),
+        Kind::Tag => token.typed.chars().count() == 2,
+        Kind::Note | Kind::Variable => false,
+    })
+}
+
+/// The variables a template can use, with the frontmatter fields of the note
+pub fn variables(fields: &[(String, Vec<String>)]) -> Vec<Candidate> {
+    VARIABLES
+        .iter()
+        .map(|variable| Candidate::new(*variable, "variable"))
+        .chain(fields.iter().map(|(name, _)| Candidate::new(name, "field")))
+        .collect()
+}
+
+/// The candidates matching what was typed, the best first. Each is kept once
+pub fn filter(candidates: Vec<Candidate>, typed: &str) -> Vec<Candidate> {
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(i64, Candidate)> = candidates
+        .into_iter()
+        .filter_map(|candidate| Some((matcher.fuzzy_match(&candidate.label, typed)?, candidate)))
+        .collect();
+    scored.sort_by(|(a, first), (b, second)| b.cmp(a).then_with(|| first.label.cmp(&second.label)));
+
+    let mut seen = HashSet::new();
+    scored
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .filter(|candidate| seen.insert(candidate.clone()))
+        .collect()
+}
@@ -0,0 +1,226 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    date::{format_date, parse_date},
+    error::VaultError,
+};
+
+/// Where the vault keeps its own state, next to the notes
+pub const DATA_DIR: &str = ".vault-tui";
+
+/// The names `:set` knows, completed by Tab
+pub const OPTIONS: [&str; 11] = [
+    "ignorecase",
+    "ic",
+    "smartcase",
+    "scs",
+    "templates",
+    "note_name",
+    "daily_folder",
+    "daily_format",
+    "date_format",
+    "conceal",
+    "autopair",
+];
+
+/// Options read from `.vault-tui/config` as `name = value` lines, and changed with `:set`
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Search without regard to case
+    pub ignorecase: bool,
+    /// With `ignorecase`, search with regard to case again when the pattern has a capital
+    pub smartcase: bool,
+    /// The folder the template picker lists
+    pub templates: String,
+    /// The file name of new notes, a template where `{{title}}` is the name typed
+    pub note_name: String,
+    /// The template new notes in a folder start from, set as `template.FOLDER = PATH`
+    pub folder_templates: Vec<(String, String)>,
+    /// The folder of the daily notes, the vault itself when empty
+    pub daily_folder: String,
+    /// The Moment format daily notes are named with
+    pub daily_format: String,
+    /// The Moment format `:date` inserts dates in
+    pub date_format: String,
+    /// Hide the markup of links and emphasis on every line but the cursor's
+    pub conceal: bool,
+    /// Close brackets, quotes, emphasis and code blocks as they are typed
+    pub autopair: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ignorecase: false,
+            smartcase: false,
+            templates: "templates".to_string(),
+            note_name: "{{date:YMMDDHHmm-}}{{title}}".to_string(),
+            folder_templates: Vec::new(),
+            daily_folder: String::new(),
+            daily_format: "YYYY-MM-DD".to_string(),
+            date_format: "YYYY-MM-DD".to_string(),
+            conceal: false,
+            autopair: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(&mut self) -> Result<(), VaultError> {
+        let path = Path::new(DATA_DIR).join("config");
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Ok(());
+        };
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // The same `name`, `noname` and `name = value` as `:set`
+            let result = match line.split_once('=') {
+                Some((name, value)) => self.set_value(name.trim(), value.trim()),
+                None => self.set(line),
+            };
+            result.map_err(|error| match error {
+                VaultError::Command(message) => VaultError::Command(format!(
+                    "{} line {}: {}",
+                    path.display(),
+                    number + 1,
+                    message
+                )),
+                error => error,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply one argument of `:set`: `name`, `noname`, `name!` or `name=value`
+    pub fn set(&mut self, argument: &str) -> Result<(), VaultError> {
+        if let Some((name, value)) = argument.split_once('=') {
+            return self.set_value(name, value);
+        }
+        if let Some(name) = argument.strip_suffix('!') {
+            let value = !*self.flag(name)?;
+            *self.flag(name)? = value;
+            return Ok(());
+        }
+        match argument.strip_prefix("no") {
+            Some(name) if self.flag(name).is_ok() => *self.flag(name)? = false,
+            _ => *self.flag(argument)? = true,
+        }
+        Ok(())
+    }
+
+    fn set_value(&mut self, name: &str, value: &str) -> Result<(), VaultError> {
+        match name {
+            "templates" => {
+                self.templates = value.trim_end_matches('/').to_string();
+                return Ok(());
+            }
+            "note_name" => {
+                self.note_name = value.to_string();
+                return Ok(());
+            }
+            "daily_folder" => {
+                self.daily_folder = value.trim_end_matches('/').to_string();
+                return Ok(());
+            }
+            "daily_format" => {
+                self.daily_format = value.to_string();
+                return Ok(());
+            }
+            "date_format" => {
+                self.date_format = value.to_string();
+                return Ok(());
+            }
+            _ => (),
+        }
+        if let Some(folder) = name.strip_prefix("template.") {
+            let folder = folder.trim_end_matches('/').to_string();
+            self.folder_templates.retain(|(other, _)| *other != folder);
+            self.folder_templates.push((folder, value.to_string()));
+            return Ok(());
+        }
+
+        let flag = self.flag(name)?;
+        *flag = match value {
+            "true" | "on" | "yes" => true,
+            "false" | "off" | "no" => false,
+            _ => {
+                return Err(VaultError::Command(format!(
+                    "Invalid value for {}: {}",
+                    name, value
+                )));
+            }
+        };
+        Ok(())
+    }
+
+    fn flag(&mut self, name: &str) -> Result<&mut bool, VaultError> {
+        match name {
+            "ignorecase" | "ic" => Ok(&mut self.ignorecase),
+            "smartcase" | "scs" => Ok(&mut self.smartcase),
+            "conceal" => Ok(&mut self.conceal),
+            "autopair" => Ok(&mut self.autopair),
+            _ => Err(VaultError::Command(format!("Unknown option: {}", name))),
+        }
+    }
+
+    /// The options and their values, as shown by `:set` without arguments
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "{}ignorecase {}smartcase {}conceal {}autopair templates={} note_name={} daily_folder={} daily_format={} date_format={}",
+            if self.ignorecase { "" } else { "no" },
+            if self.smartcase { "" } else { "no" },
+            if self.conceal { "" } else { "no" },
+            if self.autopair { "" } else { "no" },
+            self.templates,
+            self.note_name,
+            self.daily_folder,
+            self.daily_format,
+            self.date_format,
+        );
+        for (folder, template) in &self.folder_templates {
+            description += &format!(" template.{}={}", folder, template);
+        }
+        description
+    }
+
+    /// The default template of new notes in `folder`, from the closest folder that has one
+    pub fn folder_template(&self, folder: &Path) -> Option<&str> {
+        folder.ancestors().find_map(|ancestor| {
+            self.folder_templates
+                .iter()
+                .find(|(other, _)| Path::new(other) == ancestor)
+                .map(|(_, template)| template.as_str())
+        })
+    }
+
+    /// The path of the daily note of a date
+    pub fn daily_note(&self, date: NaiveDate) -> PathBuf {
+        let date = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        Path::new(&self.daily_folder).join(format_date(&date, &self.daily_format) + ".md")
+    }
+
+    /// The date of the daily note a link points to, written with or without its folder
+    pub fn daily_date(&self, link: &str) -> Option<NaiveDate> {
+        let name = link.trim_end_matches(".md");
+        let name = name
+            .strip_prefix(&format!("{}/", self.daily_folder))
+            .unwrap_or(name);
+        parse_date(name, &self.daily_format)
+    }
+
+    /// Whether a search for `pattern` ignores case
+    pub fn ignores_case(&self, pattern: &str) -> bool {
+        self.ignorecase && !(self.smartcase && pattern.chars().any(char::is_uppercase))
+    }
+}
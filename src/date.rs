@@ -0,0 +1,673 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Offset, TimeZone, Timelike};
+
+/// The Moment.js tokens, longest first so that `MMMM` is not read as four `M`.
+/// Era tokens (`N`, `y`) and time zone names (`z`) are not supported
+const TOKENS: [&str; 70] = [
+    "YYYYYY",
+    "YYYYY",
+    "YYYY",
+    "YY",
+    "Y",
+    "MMMM",
+    "MMM",
+    "MM",
+    "Mo",
+    "M",
+    "Qo",
+    "Q",
+    "DDDD",
+    "DDDo",
+    "DDD",
+    "DD",
+    "Do",
+    "D",
+    "dddd",
+    "ddd",
+    "dd",
+    "do",
+    "d",
+    "e",
+    "E",
+    "wo",
+    "ww",
+    "w",
+    "Wo",
+    "WW",
+    "W",
+    "gggg",
+    "gg",
+    "GGGG",
+    "GG",
+    "A",
+    "a",
+    "HH",
+    "H",
+    "hh",
+    "h",
+    "kk",
+    "k",
+    "mm",
+    "m",
+    "ss",
+    "s",
+    "SSSSSSSSS",
+    "SSSSSSSS",
+    "SSSSSSS",
+    "SSSSSS",
+    "SSSSS",
+    "SSSS",
+    "SSS",
+    "SS",
+    "S",
+    "X",
+    "x",
+    "ZZ",
+    "Z",
+    "LTS",
+    "LT",
+    "LLLL",
+    "LLL",
+    "LL",
+    "L",
+    "llll",
+    "lll",
+    "ll",
+    "l",
+];
+
+/// The localized formats of the English locale
+const LOCALIZED: [(&str, &str); 10] = [
+    ("LTS", "h:mm:ss A"),
+    ("LT", "h:mm A"),
+    ("LLLL", "dddd, MMMM D, YYYY h:mm A"),
+    ("LLL", "MMMM D, YYYY h:mm A"),
+    ("LL", "MMMM D, YYYY"),
+    ("L", "MM/DD/YYYY"),
+    ("llll", "ddd, MMM D, YYYY h:mm A"),
+    ("lll", "MMM D, YYYY h:mm A"),
+    ("ll", "MMM D, YYYY"),
+    ("l", "M/D/YYYY"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Literal(&'a str),
+    Pattern(&'a str),
+}
+
+/// Split a Moment.js format into tokens and the text between them.
+/// `[text]` and `\c` are always literal, as are characters that are not a token
+fn tokenize(format: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = format;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '['
+            && let Some(end) = rest.find(']')
+        {
+            tokens.push(Token::Literal(&rest[1..end]));
+            rest = &rest[end + 1..];
+            continue;
+        }
+        if c == '\\'
+            && let Some(escaped) = rest[1..].chars().next()
+        {
+            tokens.push(Token::Literal(&rest[1..1 + escaped.len_utf8()]));
+            rest = &rest[1 + escaped.len_utf8()..];
+            continue;
+        }
+
+        let pattern = TOKENS
+            .iter()
+            .filter(|token| rest.starts_with(**token))
+            .max_by_key(|token| token.len());
+        match pattern {
+            Some(pattern) => {
+                tokens.push(Token::Pattern(pattern));
+                rest = &rest[pattern.len()..];
+            }
+            None => {
+                tokens.push(Token::Literal(&rest[..c.len_utf8()]));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Format a date the way Moment.js, and so Obsidian, does with the English locale
+pub fn format_date<Tz: TimeZone>(date: &DateTime<Tz>, format: &str) -> String {
+    let mut formatted = String::new();
+
+    for token in tokenize(format) {
+        match token {
+            Token::Literal(text) => formatted += text,
+            Token::Pattern(pattern) => formatted += &format_token(date, pattern),
+        }
+    }
+
+    formatted
+}
+
+fn format_token<Tz: TimeZone>(date: &DateTime<Tz>, pattern: &str) -> String {
+    if let Some((_, expansion)) = LOCALIZED.iter().find(|(token, _)| *token == pattern) {
+        return format_date(date, expansion);
+    }
+
+    let year = date.year();
+    let hour12 = match date.hour() % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    let millisecond = date.timestamp_subsec_millis();
+    let (week_year, week) = locale_week(date.date_naive());
+    let iso_week = date.iso_week();
+    let offset = date.offset().fix().local_minus_utc();
+
+    match pattern {
+        "YYYYYY" => format!("{}{:06}", if year < 0 { '-' } else { '+' }, year.abs()),
+        "YYYYY" => format!("{:05}", year),
+        "YYYY" | "Y" if year > 9999 => format!("+{}", year),
+        "YYYY" | "Y" => format!("{:04}", year),
+        "YY" => format!("{:02}", year.rem_euclid(100)),
+        "MMMM" => date.naive_local().format("%B").to_string(),
+        "MMM" => date.naive_local().format("%b").to_string(),
+        "MM" => format!("{:02}", date.month()),
+        "Mo" => ordinal(date.month()),
+        "M" => date.month().to_string(),
+        "Qo" => ordinal(date.month0() / 3 + 1),
+        "Q" => (date.month0() / 3 + 1).to_string(),
+        "DDDD" => format!("{:03}", date.ordinal()),
+        "DDDo" => ordinal(date.ordinal()),
+        "DDD" => date.ordinal().to_string(),
+        "DD" => format!("{:02}", date.day()),
+        "Do" => ordinal(date.day()),
+        "D" => date.day().to_string(),
+        "dddd" => date.naive_local().format("%A").to_string(),
+        "ddd" => date.naive_local().format("%a").to_string(),
+        "dd" => date.naive_local().format("%a").to_string()[..2].to_string(),
+        "do" => ordinal(date.weekday().num_days_from_sunday()),
+        "d" | "e" => date.weekday().num_days_from_sunday().to_string(),
+        "E" => date.weekday().number_from_monday().to_string(),
+        "wo" => ordinal(week),
+        "ww" => format!("{:02}", week),
+        "w" => week.to_string(),
+        "Wo" => ordinal(iso_week.week()),
+        "WW" => format!("{:02}", iso_week.week()),
+        "W" => iso_week.week().to_string(),
+        "gggg" => format!("{:04}", week_year),
+        "gg" => format!("{:02}", week_year.rem_euclid(100)),
+        "GGGG" => format!("{:04}", iso_week.year()),
+        "GG" => format!("{:02}", iso_week.year().rem_euclid(100)),
+        "A" => (if date.hour() < 12 { "AM" } else { "PM" }).to_string(),
+        "a" => (if date.hour() < 12 { "am" } else { "pm" }).to_string(),
+        "HH" => format!("{:02}", date.hour()),
+        "H" => date.hour().to_string(),
+        "hh" => format!("{:02}", hour12),
+        "h" => hour12.to_string(),
+        "kk" => format!("{:02}", if date.hour() == 0 { 24 } else { date.hour() }),
+        "k" => (if date.hour() == 0 { 24 } else { date.hour() }).to_string(),
+        "mm" => format!("{:02}", date.minute()),
+        "m" => date.minute().to_string(),
+        "ss" => format!("{:02}", date.second()),
+        "s" => date.second().to_string(),
+        // Like Moment, fractions only go down to milliseconds and are padded with zeros after
+        "S" => (millisecond / 100).to_string(),
+        "SS" => format!("{:02}", millisecond / 10),
+        fraction if fraction.starts_with('S') => {
+            format!("{:03}", millisecond) + &"0".repeat(fraction.len() - 3)
+        }
+        "X" => date.timestamp().to_string(),
+        "x" => date.timestamp_millis().to_string(),
+        "ZZ" | "Z" => {
+            let sign = if offset < 0 { '-' } else { '+' };
+            let (hours, minutes) = (offset.abs() / 3600, offset.abs() / 60 % 60);
+            let separator = if pattern == "Z" { ":" } else { "" };
+            format!("{}{:02}{}{:02}", sign, hours, separator, minutes)
+        }
+        _ => pattern.to_string(),
+    }
+}
+
+/// The week year and week of a date in the English locale: weeks start on Sunday
+/// and week 1 is the one with January 1st in it
+fn locale_week(date: NaiveDate) -> (i32, u32) {
+    let week_start = date - Duration::days(date.weekday().num_days_from_sunday() as i64);
+    // The week belongs to the year its Saturday is in
+    let week_year = (week_start + Duration::days(6)).year();
+    let january_first = NaiveDate::from_ymd_opt(week_year, 1, 1).unwrap();
+    let first_week_start =
+        january_first - Duration::days(january_first.weekday().num_days_from_sunday() as i64);
+
+    let week = (week_start - first_week_start).num_days() / 7 + 1;
+    (week_year, week as u32)
+}
+
+/// `1st`, `2nd`, `3rd`, `4th`, `11th`, ...
+fn ordinal(number: u32) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// The words of the phrases `parse_natural` understands, completed by Tab after `:date`
+pub const PHRASE_WORDS: [&str; 21] = [
+    "today",
+    "tomorrow",
+    "yesterday",
+    "next",
+    "last",
+    "this",
+    "in",
+    "ago",
+    "days",
+    "weeks",
+    "months",
+    "years",
+    "week",
+    "month",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Read back a date written in a Moment.js format. Only the tokens that make up a date
+/// are understood, and weekdays are skipped over rather than checked
+pub fn parse_date(text: &str, format: &str) -> Option<NaiveDate> {
+    let (mut year, mut month, mut day, mut day_of_year) = (None, None, None, None);
+    let mut rest = text;
+
+    let mut tokens = tokenize(format);
+    tokens.reverse();
+    while let Some(token) = tokens.pop() {
+        let pattern = match token {
+            Token::Literal(literal) => {
+                rest = rest.strip_prefix(literal)?;
+                continue;
+            }
+            Token::Pattern(pattern) => pattern,
+        };
+        if let Some((_, expansion)) = LOCALIZED.iter().find(|(token, _)| *token == pattern) {
+            tokens.extend(tokenize(expansion).into_iter().rev());
+            continue;
+        }
+
+        let (value, len) = match pattern {
+            "YYYY" | "Y" => number(rest, 4, 4)?,
+            "YY" => {
+                let (value, len) = number(rest, 2, 2)?;
+                // Moment reads 69 to 99 as the 1900s
+                (value + if value > 68 { 1900 } else { 2000 }, len)
+            }
+            "MMMM" => name(rest, &MONTHS, usize::MAX)?,
+            "MMM" => name(rest, &MONTHS, 3)?,
+            "MM" => number(rest, 2, 2)?,
+            "M" => number(rest, 1, 2)?,
+            "Mo" | "Do" | "DDDo" => {
+                let (value, len) = number(rest, 1, 3)?;
+                let suffix = &ordinal(value)[len..];
+                (
+                    value,
+                    len + rest[len..].strip_prefix(suffix).map(|_| suffix.len())?,
+                )
+            }
+            "DDDD" => number(rest, 3, 3)?,
+            "DDD" => number(rest, 1, 3)?,
+            "DD" => number(rest, 2, 2)?,
+            "D" => number(rest, 1, 2)?,
+            "dddd" => name(rest, &WEEKDAYS, usize::MAX)?,
+            "ddd" => name(rest, &WEEKDAYS, 3)?,
+            "dd" => name(rest, &WEEKDAYS, 2)?,
+            "d" | "e" | "E" => number(rest, 1, 1)?,
+            _ => return None,
+        };
+        match pattern {
+            "YYYY" | "Y" | "YY" => year = Some(value as i32),
+            "MMMM" | "MMM" => month = Some(value + 1),
+            "MM" | "M" | "Mo" => month = Some(value),
+            "DDDD" | "DDD" | "DDDo" => day_of_year = Some(value),
+            "DD" | "D" | "Do" => day = Some(value),
+            _ => (),
+        }
+        rest = &rest[len..];
+    }
+
+    if !rest.is_empty() {
+        return None;
+    }
+    match (year?, month, day, day_of_year) {
+        (year, Some(month), Some(day), _) => NaiveDate::from_ymd_opt(year, month, day),
+        (year, None, None, Some(day_of_year)) => NaiveDate::from_yo_opt(year, day_of_year),
+        _ => None,
+    }
+}
+
+/// The number at the start of `text`, of at least `min` and at most `max` digits, and its length
+fn number(text: &str, min: usize, max: usize) -> Option<(u32, usize)> {
+    let len = text
+        .bytes()
+        .take(max)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    (len >= min).then(|| (text[..len].parse().unwrap(), len))
+}
+
+/// The index of the name `text` starts with, shortened to `len` characters, and its length
+fn name(text: &str, names: &[&str], len: usize) -> Option<(u32, usize)> {
+    names.iter().enumerate().find_map(|(index, name)| {
+        let name = &name[..len.min(name.len())];
+        text.starts_with(name).then_some((index as u32, name.len()))
+    })
+}
+
+/// The date a phrase like `today`, `friday`, `next friday`, `last month`, `in 3 days`
+/// or `2 weeks ago` means, counted from `today`. Dates written as YYYY-MM-DD are read as they are
+pub fn parse_natural(phrase: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let phrase = phrase.trim().to_lowercase();
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["today" | "now"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        [word] => match weekday(word) {
+            Some(weekday) => weekday_after(today, weekday, false),
+            None => parse_date(word, "YYYY-MM-DD"),
+        },
+        ["this", word] => weekday_after(today, weekday(word)?, false),
+        ["next", word] => match weekday(word) {
+            Some(weekday) => weekday_after(today, weekday, true),
+            None => shift(today, 1, word),
+        },
+        ["last", word] => match weekday(word) {
+            Some(weekday) => {
+                let back = (today.weekday().num_days_from_sunday() + 7 - weekday) % 7;
+                today.checked_sub_signed(Duration::days(if back == 0 { 7 } else { back } as i64))
+            }
+            None => shift(today, -1, word),
+        },
+        ["in", count, unit] => shift(today, amount(count)?, unit),
+        [count, unit, "ago"] => shift(today, -amount(count)?, unit),
+        _ => None,
+    }
+}
+
+/// The day of the week a name or its first three letters mean, counted from Sunday
+fn weekday(word: &str) -> Option<u32> {
+    WEEKDAYS
+        .iter()
+        .position(|name| {
+            let name = name.to_lowercase();
+            word == name || word == &name[..3]
+        })
+        .map(|index| index as u32)
+}
+
+/// The first `weekday` from `today` on, or after it when `strictly`
+fn weekday_after(today: NaiveDate, weekday: u32, strictly: bool) -> Option<NaiveDate> {
+    let ahead = (weekday + 7 - today.weekday().num_days_from_sunday()) % 7;
+    let ahead = if ahead == 0 && strictly { 7 } else { ahead };
+    today.checked_add_signed(Duration::days(ahead as i64))
+}
+
+fn amount(count: &str) -> Option<i64> {
+    const WORDS: [&str; 10] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    match count {
+        "a" | "an" => Some(1),
+        _ => match WORDS.iter().position(|word| *word == count) {
+            Some(index) => Some(index as i64 + 1),
+            None => count.parse().ok(),
+        },
+    }
+}
+
+/// Move a date by `amount` days, weeks, months or years
+fn shift(date: NaiveDate, amount: i64, unit: &str) -> Option<NaiveDate> {
+    let count = match unit.trim_end_matches('s') {
+        "day" => return date.checked_add_signed(Duration::try_days(amount)?),
+        "week" => return date.checked_add_signed(Duration::try_weeks(amount)?),
+        "month" => amount,
+        "year" => amount.checked_mul(12)?,
+        _ => return None,
+    };
+    let months = Months::new(u32::try_from(count.unsigned_abs()).ok()?);
+    if count < 0 {
+        date.checked_sub_months(months)
+    } else {
+        date.checked_add_months(months)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Utc};
+
+    use super::*;
+
+    /// Sunday 18 October 2026, 14:05:09.123 at UTC+2
+    fn date() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 18, 14, 5, 9)
+            .unwrap()
+            + Duration::milliseconds(123)
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn years_months_and_days() {
+        assert_eq!(format_date(&date(), "YYYY-MM-DD"), "2026-10-18");
+        assert_eq!(
+            format_date(&date(), "YY Y YYYYY YYYYYY"),
+            "26 2026 02026 +002026"
+        );
+        assert_eq!(
+            format_date(&date(), "M Mo MM MMM MMMM"),
+            "10 10th 10 Oct October"
+        );
+        assert_eq!(format_date(&date(), "D Do DD"), "18 18th 18");
+        assert_eq!(format_date(&date(), "DDD DDDo DDDD"), "291 291st 291");
+        assert_eq!(format_date(&at(2026, 1, 5, 0), "DDDD"), "005");
+        assert_eq!(format_date(&date(), "Q Qo"), "4 4th");
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(
+            format_date(&date(), "d do dd ddd dddd"),
+            "0 0th Su Sun Sunday"
+        );
+        assert_eq!(format_date(&date(), "e E"), "0 7");
+        assert_eq!(
+            format_date(&at(2026, 10, 21, 0), "d E dddd"),
+            "3 3 Wednesday"
+        );
+    }
+
+    #[test]
+    fn weeks() {
+        assert_eq!(
+            format_date(&date(), "w wo ww gggg gg"),
+            "43 43rd 43 2026 26"
+        );
+        assert_eq!(
+            format_date(&date(), "W Wo WW GGGG GG"),
+            "42 42nd 42 2026 26"
+        );
+        // The week with January 1st in it is already week 1 of the next year
+        assert_eq!(format_date(&at(2026, 12, 31, 0), "w gggg"), "1 2027");
+        // While in ISO weeks 2026 has a week 53
+        assert_eq!(format_date(&at(2026, 12, 31, 0), "W GGGG"), "53 2026");
+        assert_eq!(format_date(&at(2027, 1, 1, 0), "W GGGG"), "53 2026");
+        assert_eq!(format_date(&at(2026, 1, 4, 0), "w ww"), "2 02");
+    }
+
+    #[test]
+    fn hours_minutes_and_seconds() {
+        assert_eq!(format_date(&date(), "H HH h hh k kk"), "14 14 2 02 14 14");
+        assert_eq!(format_date(&date(), "h:mm:ss a"), "2:05:09 pm");
+        assert_eq!(format_date(&date(), "hh A"), "02 PM");
+        assert_eq!(format_date(&at(2026, 10, 18, 0), "H h k A"), "0 12 24 AM");
+        assert_eq!(format_date(&at(2026, 10, 18, 12), "h a"), "12 pm");
+        assert_eq!(format_date(&date(), "m mm s ss"), "5 05 9 09");
+        assert_eq!(format_date(&date(), "S SS SSS SSSS"), "1 12 123 1230");
+    }
+
+    #[test]
+    fn time_zones_and_timestamps() {
+        assert_eq!(format_date(&date(), "Z ZZ"), "+02:00 +0200");
+        assert_eq!(format_date(&at(2026, 10, 18, 0), "Z"), "+00:00");
+        let billennium = Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap();
+        assert_eq!(format_date(&billennium, "X"), "1000000000");
+        assert_eq!(format_date(&billennium, "x"), "1000000000000");
+    }
+
+    #[test]
+    fn localized_formats() {
+        assert_eq!(format_date(&date(), "LT"), "2:05 PM");
+        assert_eq!(format_date(&date(), "LTS"), "2:05:09 PM");
+        assert_eq!(format_date(&date(), "L"), "10/18/2026");
+        assert_eq!(format_date(&date(), "LL"), "October 18, 2026");
+        assert_eq!(
+            format_date(&date(), "LLLL"),
+            "Sunday, October 18, 2026 2:05 PM"
+        );
+        assert_eq!(format_date(&date(), "l"), "10/18/2026");
+        assert_eq!(format_date(&date(), "ll"), "Oct 18, 2026");
+        assert_eq!(format_date(&date(), "llll"), "Sun, Oct 18, 2026 2:05 PM");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(format_date(&date(), "[Today is] dddd"), "Today is Sunday");
+        assert_eq!(format_date(&date(), "YYYY [YYYY]"), "2026 YYYY");
+        assert_eq!(format_date(&date(), "H\\hmm"), "14h05");
+        assert_eq!(format_date(&date(), "YYYY-MM-DDTHH:mm"), "2026-10-18T14:05");
+        assert_eq!(format_date(&date(), "[YYYY"), "[2026");
+        assert_eq!(format_date(&date(), "dddd 📅"), "Sunday 📅");
+    }
+
+    #[test]
+    fn formats_without_separators() {
+        assert_eq!(format_date(&date(), "YMMDDHHmm-"), "202610181405-");
+        assert_eq!(format_date(&date(), "YYYYMMDD"), "20261018");
+    }
+
+    #[test]
+    fn ordinals() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 23, 101, 111, 112]
+            .into_iter()
+            .map(ordinal)
+            .collect();
+        assert_eq!(
+            ordinals,
+            [
+                "1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "23rd",
+                "101st", "111th", "112th"
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("MMMM Do [at] h"),
+            [
+                Token::Pattern("MMMM"),
+                Token::Literal(" "),
+                Token::Pattern("Do"),
+                Token::Literal(" "),
+                Token::Literal("at"),
+                Token::Literal(" "),
+                Token::Pattern("h"),
+            ]
+        );
+    }
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse_date("2026-10-18", "YYYY-MM-DD"), Some(day(10, 18)));
+        assert_eq!(parse_date("18 October 26", "D MMMM YY"), Some(day(10, 18)));
+        assert_eq!(
+            parse_date("Sun, Oct 18th 2026", "ddd, MMM Do YYYY"),
+            Some(day(10, 18))
+        );
+        assert_eq!(parse_date("2026-291", "YYYY-DDDD"), Some(day(10, 18)));
+        assert_eq!(parse_date("10/18/2026", "L"), Some(day(10, 18)));
+        assert_eq!(parse_date("[2026] 1.5", "[[]YYYY] M.D"), Some(day(1, 5)));
+        assert_eq!(parse_date("2026-10-18 notes", "YYYY-MM-DD"), None);
+        assert_eq!(parse_date("2026-02-30", "YYYY-MM-DD"), None);
+        assert_eq!(parse_date("2026-10-18", "YYYY-MM-DD HH:mm"), None);
+        assert_eq!(parse_date("18 October", "D MMMM"), None);
+    }
+
+    #[test]
+    fn natural_dates() {
+        let today = day(10, 18);
+        assert_eq!(parse_natural("today", today), Some(today));
+        assert_eq!(parse_natural("Tomorrow", today), Some(day(10, 19)));
+        assert_eq!(parse_natural("yesterday", today), Some(day(10, 17)));
+        assert_eq!(parse_natural("friday", today), Some(day(10, 23)));
+        assert_eq!(parse_natural("sunday", today), Some(today));
+        assert_eq!(parse_natural("this fri", today), Some(day(10, 23)));
+        assert_eq!(parse_natural("next friday", today), Some(day(10, 23)));
+        assert_eq!(parse_natural("next sunday", today), Some(day(10, 25)));
+        assert_eq!(parse_natural("last friday", today), Some(day(10, 16)));
+        assert_eq!(parse_natural("last sunday", today), Some(day(10, 11)));
+        assert_eq!(parse_natural("next week", today), Some(day(10, 25)));
+        assert_eq!(parse_natural("last month", today), Some(day(9, 18)));
+        assert_eq!(parse_natural("in 3 days", today), Some(day(10, 21)));
+        assert_eq!(
+            parse_natural("in a year", today),
+            parse_date("2027-10-18", "YYYY-MM-DD")
+        );
+        assert_eq!(parse_natural("two weeks ago", today), Some(day(10, 4)));
+        assert_eq!(parse_natural("2026-12-01", today), Some(day(12, 1)));
+        assert_eq!(parse_natural("someday", today), None);
+        assert_eq!(parse_natural("in 3 fortnights", today), None);
+    }
+}
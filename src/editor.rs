@@ -1,21 +1,48 @@
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{BufRead, BufReader, BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use ratatui::style::Style;
+use ratatui::{
+    Frame,
+    layout::{Position, Rect},
+    style::Style,
+};
+use regex::Regex;
 use tui_textarea::TextArea;
 
-use crate::{error::VaultError, vault::get_formated_date};
+use crate::{
+    error::VaultError,
+    fold::{self, FoldCommand, Folds},
+    markdown::Highlighter,
+    vault::get_formated_date,
+    view::{jump, render_textarea},
+    vim::{Mode, first_non_blank, select_lines},
+};
 
 #[derive(Debug, Clone)]
 pub struct Editor<'a> {
     pub textareas: Vec<TextArea<'a>>,
     pub paths: Vec<PathBuf>,
+    /// The Markdown styles of each buffer
+    pub highlighters: Vec<Highlighter>,
+    /// The closed folds of each buffer
+    pub folds: Vec<Folds>,
+    /// The whole-text changes of each buffer, undone and redone in one step
+    replaced: Vec<Replaced>,
     pub current: usize,
 }
 
+/// The text of a buffer before and after each change made by `Editor::set_lines`. The
+/// textarea records one as deleting the text and inserting the new one, two steps that
+/// undo and redo take together
+#[derive(Debug, Clone, Default)]
+struct Replaced {
+    undo: Vec<(Vec<String>, Vec<String>)>,
+    redo: Vec<(Vec<String>, Vec<String>)>,
+}
+
 impl Editor<'_> {
     pub fn new() -> Self {
         let textarea = TextArea::new(vec![
@@ -25,6 +52,9 @@ impl Editor<'_> {
         Self {
             textareas: vec![textarea],
             paths: vec![path],
+            highlighters: vec![Highlighter::default()],
+            folds: vec![Folds::default()],
+            replaced: vec![Replaced::default()],
             current: 0,
         }
     }
@@ -33,12 +63,85 @@ impl Editor<'_> {
         &self.paths[self.current]
     }
 
-    pub fn textarea(&self) -> &TextArea {
+    pub fn textarea(&self) -> &TextArea<'_> {
         &self.textareas[self.current]
     }
 
+    /// The match at or before the cursor, counted from 1, and how many matches there are
+    pub fn search_count(&self) -> Option<(usize, usize)> {
+        let textarea = self.textarea();
+        let pattern = textarea.search_pattern()?;
+        let cursor = textarea.cursor();
+
+        let mut current = 0;
+        let mut total = 0;
+        for (row, line) in textarea.lines().iter().enumerate() {
+            for found in pattern.find_iter(line) {
+                total += 1;
+                if (row, line[..found.start()].chars().count()) <= cursor {
+                    current = total;
+                }
+            }
+        }
+
+        (total > 0).then_some((current, total))
+    }
+
+    /// Render the current buffer, returning where its cursor is
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        mode: Mode,
+        conceal: bool,
+    ) -> Option<Position> {
+        let textarea = &mut self.textareas[self.current];
+        let closed = self.folds[self.current].closed(textarea.lines(), textarea.cursor().0);
+        render_textarea(
+            frame,
+            area,
+            textarea,
+            &mut self.highlighters[self.current],
+            &closed,
+            mode,
+            conceal,
+        )
+    }
+
+    /// Open or close folds of the current buffer, the cursor going to the start of the
+    /// fold that closes over it
+    pub fn fold(&mut self, command: FoldCommand) -> Result<(), VaultError> {
+        let textarea = &mut self.textareas[self.current];
+        let (row, _) = textarea.cursor();
+        self.folds[self.current]
+            .exec(command, textarea.lines(), row)
+            .map_err(VaultError::Command)?;
+        self.leave_folds((row, 0));
+        Ok(())
+    }
+
+    /// Keep the cursor off the lines closed folds hide after it moved from `from`: moving
+    /// down from the line of a fold goes past the fold, anything else stops on its line
+    pub fn leave_folds(&mut self, from: (usize, usize)) {
+        let textarea = &mut self.textareas[self.current];
+        let (row, _) = textarea.cursor();
+        let closed = self.folds[self.current].closed(textarea.lines(), row);
+        let Some(fold) = fold::hiding(&closed, row) else {
+            return;
+        };
+
+        let target =
+            if from.0 == fold.start && row > from.0 && fold.end + 1 < textarea.lines().len() {
+                fold.end + 1
+            } else {
+                fold.start
+            };
+        let col = first_non_blank(&textarea.lines()[target]);
+        jump(textarea, (target, col));
+    }
+
     pub fn open(&mut self, path: PathBuf) -> Result<(), VaultError> {
-        if self.textareas.len() != 0 {
+        if !self.textareas.is_empty() {
             self.current = self.textareas.len();
         }
 
@@ -75,6 +178,9 @@ impl Editor<'_> {
 
         self.textareas.push(TextArea::new(lines));
         self.paths.push(path);
+        self.highlighters.push(Highlighter::default());
+        self.folds.push(Folds::default());
+        self.replaced.push(Replaced::default());
 
         self.textareas[self.current].set_line_number_style(Style::default());
 
@@ -82,15 +188,175 @@ impl Editor<'_> {
     }
 
     pub fn save(&self) -> Result<(), VaultError> {
-        if self.paths[self.current] != PathBuf::from("vault-tui-intro-buffer") {
-            // TODO: clean unwraps up
-            let mut file = BufWriter::new(File::create(&self.path()).unwrap());
-            for line in self.textareas[self.current].lines() {
-                file.write_all(line.as_bytes()).unwrap();
-                file.write_all(b"\n").unwrap();
-            }
+        if self.paths[self.current] != Path::new("vault-tui-intro-buffer") {
+            write_lines(self.path(), self.textareas[self.current].lines())?;
         }
 
         Ok(())
     }
+
+    /// Write the current buffer to another file, leaving the buffer pointed at its own
+    pub fn save_as(&self, path: &Path) -> Result<(), VaultError> {
+        write_lines(path, self.textareas[self.current].lines())
+    }
+
+    /// Replace the whole text of a buffer as one change, so a single undo brings it back
+    pub fn set_lines(&mut self, buffer: usize, lines: &[String]) {
+        let textarea = &mut self.textareas[buffer];
+        let cursor = textarea.cursor();
+        let before = textarea.lines().to_vec();
+
+        textarea.cancel_selection();
+        textarea.select_all();
+        textarea.insert_str(lines.join("\n"));
+
+        // Deleting or inserting no text is no step of its own
+        let replaced = &mut self.replaced[buffer];
+        replaced.redo.clear();
+        if before != [""] && lines.concat() != "" {
+            if replaced.undo.len() == textarea.max_histories() {
+                replaced.undo.remove(0);
+            }
+            replaced.undo.push((before, textarea.lines().to_vec()));
+        }
+
+        let row = cursor.0.min(textarea.lines().len() - 1);
+        let col = cursor.1.min(textarea.lines()[row].chars().count());
+        jump(textarea, (row, col));
+    }
+
+    /// Undo the last change of the current buffer, a change of `set_lines` as a whole
+    pub fn undo(&mut self) {
+        let textarea = &mut self.textareas[self.current];
+        let replaced = &mut self.replaced[self.current];
+        let lines = textarea.lines().to_vec();
+        textarea.undo();
+        if textarea.lines() == [""]
+            && replaced
+                .undo
+                .last()
+                .is_some_and(|(_, after)| *after == lines)
+        {
+            textarea.undo();
+            replaced.redo.extend(replaced.undo.pop());
+        }
+    }
+
+    /// Redo the last undone change of the current buffer, a change of `set_lines` as a
+    /// whole
+    pub fn redo(&mut self) {
+        let textarea = &mut self.textareas[self.current];
+        let replaced = &mut self.replaced[self.current];
+        let lines = textarea.lines().to_vec();
+        textarea.redo();
+        if textarea.lines() == [""]
+            && replaced
+                .redo
+                .last()
+                .is_some_and(|(before, _)| *before == lines)
+        {
+            textarea.redo();
+            replaced.undo.extend(replaced.redo.pop());
+        }
+    }
+
+    /// Replace the first or every match of `regex` on the given rows as one change,
+    /// returning how many were replaced, 0 when no row matches
+    pub fn substitute(
+        &mut self,
+        rows: &[usize],
+        regex: &Regex,
+        replacement: &str,
+        all: bool,
+    ) -> usize {
+        let mut lines = self.textareas[self.current].lines().to_vec();
+        let mut count = 0;
+        let mut last_changed: Option<usize> = None;
+
+        // Bottom up, so a replacement that breaks a line does not move the rows still to do
+        for &row in rows.iter().rev() {
+            let line = &lines[row];
+            let matches = if all {
+                regex.find_iter(line).count()
+            } else {
+                regex.is_match(line) as usize
+            };
+            if matches == 0 {
+                continue;
+            }
+
+            let replaced = if all {
+                regex.replace_all(line, replacement)
+            } else {
+                regex.replace(line, replacement)
+            }
+            .into_owned();
+            let replaced: Vec<String> = replaced.split('\n').map(str::to_string).collect();
+
+            // The lines a break adds above the last changed line move it down
+            last_changed = Some(last_changed.map_or(row, |last| last + replaced.len() - 1));
+            lines.splice(row..=row, replaced);
+            count += matches;
+        }
+
+        // Like Vim, end up on the last line that changed
+        if let Some(row) = last_changed {
+            self.set_lines(self.current, &lines);
+            jump(&mut self.textareas[self.current], (row, 0));
+        }
+        count
+    }
+
+    pub fn delete_lines(&mut self, (first, last): (usize, usize)) {
+        let textarea = &mut self.textareas[self.current];
+        select_lines(textarea, first, last);
+        textarea.cut();
+    }
+}
+
+/// The text of a note, every line ending with a newline
+pub fn contents(lines: &[String]) -> Vec<u8> {
+    let mut contents = Vec::new();
+    for line in lines {
+        contents.extend_from_slice(line.as_bytes());
+        contents.push(b'\n');
+    }
+    contents
+}
+
+/// Write a note in place, as saving does, so it keeps its permissions and owner and a
+/// symlink to it stays one
+pub fn write_lines(path: &Path, lines: &[String]) -> Result<(), VaultError> {
+    fs::write(path, contents(lines))
+        .map_err(|_| VaultError::OpenFile("Failed to write: ".to_string() + path.to_str().unwrap()))
+}
+
+/// Write next to the file first and then move it in place, so a failed write never
+/// leaves a note half written. The file a symlink points at is the one replaced, and it
+/// keeps its permissions
+pub fn write_file(path: &Path, contents: &[u8]) -> Result<(), VaultError> {
+    let error = || VaultError::OpenFile("Failed to write: ".to_string() + path.to_str().unwrap());
+
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let file_name = target.file_name().ok_or_else(error)?.to_str().unwrap();
+    let temporary = target.with_file_name(format!(".{}.vault-tui-tmp", file_name));
+    let permissions = fs::metadata(&target).map(|metadata| metadata.permissions());
+
+    let written = File::create(&temporary).and_then(|file| {
+        if let Ok(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        let mut file = BufWriter::new(file);
+        file.write_all(contents)?;
+        file.into_inner()?.sync_all()
+    });
+    if written
+        .and_then(|_| fs::rename(&temporary, &target))
+        .is_err()
+    {
+        let _ = fs::remove_file(&temporary);
+        return Err(error());
+    }
+
+    Ok(())
 }
@@ -1,4 +1,6 @@
+#[derive(Debug)]
 pub enum VaultError {
     OpenFile(String),
     Input,
+    Command(String),
 }
@@ -0,0 +1,209 @@
+use crate::markdown;
+
+/// A part of a note that can be folded: the line that stays shown, and the last line the
+/// fold hides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    fn contains(&self, row: usize) -> bool {
+        self.start <= row && row <= self.end
+    }
+
+    fn encloses(&self, other: &Fold) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+}
+
+/// What `za`, `zo`, `zc`, `zR` and `zM` do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldCommand {
+    Toggle,
+    Open,
+    Close,
+    OpenAll,
+    CloseAll,
+}
+
+/// The folds of a note: a heading down to the next heading of its level or higher, a list
+/// item over the lines indented under it, and a fenced code block.
+/// Sorted by start, the outer fold first when two start on the same line
+pub fn folds(lines: &[String]) -> Vec<Fold> {
+    let mut folds = Vec::new();
+    let mut code = vec![false; lines.len()];
+
+    let mut row = 0;
+    while row < lines.len() {
+        let Some((marker, len, _)) = markdown::fence(&lines[row]) else {
+            row += 1;
+            continue;
+        };
+        let start = row;
+        row += 1;
+        while row < lines.len()
+            && !matches!(markdown::fence(&lines[row]),
+                Some((closing, closing_len, info)) if closing == marker && closing_len >= len && info.is_empty())
+        {
+            row += 1;
+        }
+        let end = row.min(lines.len() - 1);
+        code[start..=end].fill(true);
+        if end > start {
+            folds.push(Fold { start, end });
+        }
+        row += 1;
+    }
+
+    let headings: Vec<(usize, usize)> = (0..lines.len())
+        .filter(|&row| !code[row])
+        .filter_map(|row| markdown::heading_level(&lines[row]).map(|level| (row, level)))
+        .collect();
+    for (index, &(start, level)) in headings.iter().enumerate() {
+        let next = headings[index + 1..]
+            .iter()
+            .find(|(_, other)| *other <= level)
+            .map_or(lines.len(), |(row, _)| *row);
+        // The blank lines before the next heading stay shown
+        let end = (start..next)
+            .rev()
+            .find(|&row| !lines[row].trim().is_empty())
+            .unwrap_or(start);
+        if end > start {
+            folds.push(Fold { start, end });
+        }
+    }
+
+    for start in (0..lines.len()).filter(|&row| !code[row]) {
+        if !markdown::is_list_item(&lines[start]) {
+            continue;
+        }
+        let level = indent(&lines[start]);
+        let mut end = start;
+        for (row, line) in lines.iter().enumerate().skip(start + 1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if indent(line) <= level {
+                break;
+            }
+            end = row;
+        }
+        if end > start {
+            folds.push(Fold { start, end });
+        }
+    }
+
+    folds.sort_by_key(|fold| (fold.start, std::cmp::Reverse(fold.end)));
+    folds.dedup();
+    folds
+}
+
+/// How far a line is indented, a tab counting as four spaces
+fn indent(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// The closed fold that hides a row, the outermost one when several do
+pub fn hiding(closed: &[Fold], row: usize) -> Option<Fold> {
+    closed
+        .iter()
+        .find(|fold| fold.start < row && row <= fold.end)
+        .copied()
+}
+
+/// Which folds of a buffer are closed, by the line they start on
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    closed: Vec<usize>,
+    /// How many lines the buffer had, to tell lines were added or removed
+    len: usize,
+}
+
+impl Folds {
+    /// The folds of the lines, the closed ones following the lines added or removed at the
+    /// cursor since the last call, and forgotten when they are no longer folds
+    fn update(&mut self, lines: &[String], cursor_row: usize) -> Vec<Fold> {
+        let delta = lines.len() as isize - self.len as isize;
+        self.len = lines.len();
+        if delta != 0 {
+            for start in &mut self.closed {
+                if *start > cursor_row {
+                    *start = start.saturating_add_signed(delta);
+                }
+            }
+        }
+
+        let folds = folds(lines);
+        self.closed
+            .retain(|start| folds.iter().any(|fold| fold.start == *start));
+        folds
+    }
+
+    /// The closed folds that are not inside another closed fold, in order
+    pub fn closed(&mut self, lines: &[String], cursor_row: usize) -> Vec<Fold> {
+        let folds = self.update(lines, cursor_row);
+        let mut closed: Vec<Fold> = Vec::new();
+        for fold in folds {
+            // Of the folds starting on a line, the outer one is the line's fold
+            let outer = closed.last().is_some_and(|last| last.encloses(&fold));
+            if !outer && self.closed.contains(&fold.start) {
+                closed.push(fold);
+            }
+        }
+        closed
+    }
+
+    pub fn exec(
+        &mut self,
+        command: FoldCommand,
+        lines: &[String],
+        cursor_row: usize,
+    ) -> Result<(), String> {
+        let folds = self.update(lines, cursor_row);
+        let mut folds_here: Vec<Fold> = Vec::new();
+        for fold in folds.iter().filter(|fold| fold.contains(cursor_row)) {
+            // A line starts one fold, the outermost of those starting on it
+            if !folds_here.iter().any(|other| other.start == fold.start) {
+                folds_here.push(*fold);
+            }
+        }
+        let is_closed = |fold: &Fold| self.closed.contains(&fold.start);
+        let outermost_closed = folds_here.iter().find(|fold| is_closed(fold)).copied();
+        let no_fold = || "No fold found".to_string();
+
+        match command {
+            FoldCommand::OpenAll => self.closed.clear(),
+            FoldCommand::CloseAll => self.closed = folds.iter().map(|fold| fold.start).collect(),
+            FoldCommand::Open => {
+                if outermost_closed.is_none() {
+                    return Err(no_fold());
+                }
+                self.closed
+                    .retain(|start| !folds_here.iter().any(|fold| fold.start == *start));
+            }
+            FoldCommand::Toggle if outermost_closed.is_some() => {
+                let start = outermost_closed.unwrap().start;
+                self.closed.retain(|other| *other != start);
+            }
+            FoldCommand::Close | FoldCommand::Toggle => {
+                // The innermost open fold around the cursor and any fold closed there
+                let fold = folds_here
+                    .iter()
+                    .rev()
+                    .find(|fold| {
+                        !is_closed(fold)
+                            && outermost_closed.is_none_or(|closed| fold.encloses(&closed))
+                    })
+                    .ok_or_else(no_fold)?;
+                self.closed.push(fold.start);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{config::DATA_DIR, error::VaultError};
+
+const MAX_ENTRIES: usize = 200;
+
+/// Lines entered in a prompt, oldest first, kept across sessions in the data folder
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+    entries: Vec<String>,
+}
+
+impl History {
+    pub fn load(name: &str) -> Self {
+        let path = Path::new(DATA_DIR).join(name);
+        // A missing or unreadable history just starts empty
+        let entries = fs::read_to_string(&path)
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Self { path, entries }
+    }
+
+    pub fn push(&mut self, entry: &str) -> Result<(), VaultError> {
+        if entry.trim().is_empty() {
+            return Ok(());
+        }
+
+        self.entries.retain(|old| old != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+
+        fs::create_dir_all(DATA_DIR)
+            .and_then(|_| fs::write(&self.path, self.entries.join("\n") + "\n"))
+            .map_err(|_| {
+                VaultError::Command(format!("Failed to save history to {}", self.path.display()))
+            })
+    }
+
+    pub fn get(&self, index: usize) -> &str {
+        &self.entries[index]
+    }
+
+    /// The index of the newest entry older than `from` that starts with `prefix`,
+    /// or of the newest one of all when `from` is None
+    pub fn previous(&self, prefix: &str, from: Option<usize>) -> Option<usize> {
+        let end = from.unwrap_or(self.entries.len());
+        (0..end)
+            .rev()
+            .find(|&index| self.entries[index].starts_with(prefix))
+    }
+
+    /// The index of the oldest entry newer than `from` that starts with `prefix`
+    pub fn next(&self, prefix: &str, from: usize) -> Option<usize> {
+        (from + 1..self.entries.len()).find(|&index| self.entries[index].starts_with(prefix))
+    }
+}
+
+/// Walking through a history from a prompt, filtered on what was typed before starting
+#[derive(Debug, Clone)]
+pub struct Browsing {
+    prefix: String,
+    index: Option<usize>,
+}
+
+impl Browsing {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            index: None,
+        }
+    }
+
+    /// The entry to show for Up, if there is an older one
+    pub fn older<'h>(&mut self, history: &'h History) -> Option<&'h str> {
+        let index = history.previous(&self.prefix, self.index)?;
+        self.index = Some(index);
+        Some(history.get(index))
+    }
+
+    /// The entry to show for Down, which past the newest one is what was typed
+    pub fn newer(&mut self, history: &History) -> Option<String> {
+        let index = self.index?;
+        self.index = history.next(&self.prefix, index);
+        match self.index {
+            Some(index) => Some(history.get(index).to_string()),
+            None => Some(self.prefix.clone()),
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::{ops::Range, sync::LazyLock};
+
+use regex::Regex;
+use tui_textarea::TextArea;
+
+use crate::{table::replace_lines, view::jump};
+
+/// A list item, inside quotes or not: the quote markers, the indent, the bullet or the
+/// number and its delimiter, the space after it and the checkbox of a task
+static ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^((?:\s*>)*)(\s*)(?:([-*+])|(\d{1,9})([.)]))(\s+)(\[.\]\s+)?").unwrap()
+});
+/// The markers of a quoted line
+static QUOTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:\s*>)+\s?").unwrap());
+
+/// The start of a list item
+struct Item {
+    quote: String,
+    indent: String,
+    number: Option<(u64, char)>,
+    bullet: char,
+    space: String,
+    task: bool,
+    /// Where the text of the item starts, in bytes
+    end: usize,
+}
+
+impl Item {
+    fn parse(line: &str) -> Option<Self> {
+        let captures = ITEM.captures(line)?;
+        let text = |group| captures.get(group).map_or("", |m| m.as_str());
+        Some(Self {
+            quote: text(1).to_string(),
+            indent: text(2).to_string(),
+            number: captures
+                .get(4)
+                .and_then(|number| Some((number.as_str().parse().ok()?, text(5).chars().next()?))),
+            bullet: text(3).chars().next().unwrap_or('-'),
+            space: text(6).to_string(),
+            task: captures.get(7).is_some(),
+            end: captures.get(0).unwrap().end(),
+        })
+    }
+
+    /// Where the indent of the item starts, after the quote markers and the space that
+    /// belongs to them
+    fn indent_start(&self) -> usize {
+        let quoted = !self.quote.is_empty() && self.indent.starts_with(' ');
+        self.quote.len() + usize::from(quoted)
+    }
+
+    fn level(&self) -> usize {
+        width(&self.quote) + width(&self.indent)
+    }
+
+    /// The start of the item that follows this one, an open task after a task
+    fn next(&self) -> String {
+        let marker = match self.number {
+            Some((number, delimiter)) => format!("{}{delimiter}", number + 1),
+            None => self.bullet.to_string(),
+        };
+        let task = if self.task { "[ ] " } else { "" };
+        format!("{}{}{marker}{}{task}", self.quote, self.indent, self.space)
+    }
+}
+
+/// How wide a run of whitespace is, a tab counting as four spaces
+fn width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// Enter in Insert mode on a list item or a quote. The next line starts with the same
+/// marker, the number after it in an ordered list, and Enter on an item with no text ends
+/// the list, outdenting a nested item first. Returns false when the line is neither
+pub fn new_line(textarea: &mut TextArea) -> bool {
+    let (row, col) = textarea.cursor();
+    let line = textarea.lines()[row].clone();
+    let offset = line
+        .char_indices()
+        .nth(col)
+        .map_or(line.len(), |(offset, _)| offset);
+
+    let (start, text_start) = match Item::parse(&line) {
+        Some(item) => (item.next(), item.end),
+        None => match QUOTE.find(&line) {
+            Some(quote) => (quote.as_str().to_string(), quote.end()),
+            None => return false,
+        },
+    };
+    // Before the marker, Enter only opens a line
+    if offset < text_start {
+        return false;
+    }
+
+    if line[text_start..].trim().is_empty() {
+        match Item::parse(&line) {
+            Some(item) if item.level() > width(&item.quote) && shift(textarea, row, false) => {}
+            _ => {
+                // A list in a quote ends, the quote does not
+                let quote = match Item::parse(&line) {
+                    Some(_) => QUOTE.find(&line).map_or("", |quote| quote.as_str()),
+                    None => "",
+                };
+                replace_lines(textarea, row..row + 1, &[quote.to_string()]);
+                jump(textarea, (row, quote.chars().count()));
+            }
+        }
+    } else {
+        textarea.insert_newline();
+        textarea.insert_str(start);
+    }
+    renumber(textarea);
+    true
+}
+
+/// Tab and Shift-Tab in Insert mode on a list item: indent or outdent it with the lines
+/// nested under it. Returns false when the line is not a list item
+pub fn indent(textarea: &mut TextArea, indent: bool) -> bool {
+    let row = textarea.cursor().0;
+    if Item::parse(&textarea.lines()[row]).is_none() {
+        return false;
+    }
+    shift(textarea, row, indent);
+    renumber(textarea);
+    true
+}
+
+/// Indent or outdent the item on a row and the lines under it by one level, keeping the
+/// cursor on its character. Returns false when there is nothing to outdent
+fn shift(textarea: &mut TextArea, row: usize, indent: bool) -> bool {
+    let lines = textarea.lines();
+    let Some(item) = Item::parse(&lines[row]) else {
+        return false;
+    };
+    let unit = textarea.indent().to_string();
+    let rows = row..nested_end(lines, row, item.level());
+
+    let removed = if indent {
+        0
+    } else if item.indent[item.indent_start() - item.quote.len()..].starts_with('\t') {
+        1
+    } else {
+        item.indent[item.indent_start() - item.quote.len()..]
+            .chars()
+            .take(unit.len())
+            .take_while(|c| *c == ' ')
+            .count()
+    };
+    if !indent && removed == 0 {
+        return false;
+    }
+
+    let shifted: Vec<String> = lines[rows.clone()]
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                return line.clone();
+            }
+            let start = Item::parse(line).map_or_else(
+                || QUOTE.find(line).map_or(0, |quote| quote.end()),
+                |item| item.indent_start(),
+            );
+            let (before, after) = line.split_at(start);
+            if indent {
+                format!("{before}{unit}{after}")
+            } else {
+                let removed = after
+                    .chars()
+                    .take(removed)
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+                format!("{before}{}", &after[removed..])
+            }
+        })
+        .collect();
+
+    let (_, col) = textarea.cursor();
+    let col = if indent {
+        col + unit.chars().count()
+    } else {
+        col.saturating_sub(removed)
+    };
+    replace_lines(textarea, rows, &shifted);
+    jump(textarea, (row, col));
+    true
+}
+
+/// The row after the lines nested under an item, blank lines in between included
+fn nested_end(lines: &[String], row: usize, level: usize) -> usize {
+    let mut end = row + 1;
+    for (next, line) in lines.iter().enumerate().skip(row + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let nested = match Item::parse(line) {
+            Some(item) => item.level() > level,
+            None => width(&line[..line.len() - line.trim_start().len()]) > level,
+        };
+        if !nested {
+            break;
+        }
+        end = next + 1;
+    }
+    end
+}
+
+/// The lines around a row with no blank line between them
+fn paragraph(lines: &[String], row: usize) -> Range<usize> {
+    let is_blank = |row: &usize| lines[*row].trim().is_empty();
+    let start = (0..row).rev().find(is_blank).map_or(0, |blank| blank + 1);
+    let end = (row..lines.len()).find(is_blank).unwrap_or(lines.len());
+    start..end
+}
+
+/// Number the items of the ordered lists around the cursor one after the other, keeping
+/// the cursor on its character
+pub fn renumber(textarea: &mut TextArea) {
+    let (row, col) = textarea.cursor();
+    let rows = paragraph(textarea.lines(), row);
+    if rows.is_empty() {
+        return;
+    }
+
+    // The level and the next number of the lists the line is in, the innermost last
+    let mut lists: Vec<(usize, Option<u64>)> = Vec::new();
+    let mut lines = textarea.lines()[rows.clone()].to_vec();
+    let mut cursor_col = col;
+    for (index, line) in lines.iter_mut().enumerate() {
+        let Some(item) = Item::parse(line) else {
+            continue;
+        };
+        let level = item.level();
+        while lists.last().is_some_and(|(other, _)| *other > level) {
+            lists.pop();
+        }
+        let number = match lists.last_mut() {
+            Some((other, next)) if *other == level => {
+                let number = item.number.and(*next);
+                *next = item.number.map(|(first, _)| number.unwrap_or(first) + 1);
+                number
+            }
+            _ => {
+                // A nested list counts from one, a list of its own from its first item
+                let number = if lists.is_empty() {
+                    None
+                } else {
+                    item.number.map(|_| 1)
+                };
+                lists.push((
+                    level,
+                    item.number.map(|(first, _)| number.unwrap_or(first) + 1),
+                ));
+                number
+            }
+        };
+
+        let (Some(number), Some((old, delimiter))) = (number, item.number) else {
+            continue;
+        };
+        if number == old {
+            continue;
+        }
+        let start = item.quote.len() + item.indent.len();
+        let marker_end = start + old.to_string().len() + delimiter.len_utf8();
+        let renumbered = format!("{}{number}{}", &line[..start], &line[marker_end - 1..]);
+        if rows.start + index == row && col > start {
+            cursor_col = (col + number.to_string().len()).saturating_sub(old.to_string().len());
+        }
+        *line = renumbered;
+    }
+
+    replace_lines(textarea, rows, &lines);
+    jump(textarea, (row, cursor_col));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A textarea holding some lines with the cursor at a position
+    fn textarea(lines: &[&str], cursor: (usize, usize)) -> TextArea<'static> {
+        let mut textarea = TextArea::new(lines.iter().map(|line| line.to_string()).collect());
+        jump(&mut textarea, cursor);
+        textarea
+    }
+
+    #[test]
+    fn continuing_items() {
+        let mut list = textarea(&["- one"], (0, 5));
+        assert!(new_line(&mut list));
+        assert_eq!(list.lines(), ["- one", "- "]);
+        assert_eq!(list.cursor(), (1, 2));
+
+        let mut list = textarea(&["  * [x] done"], (0, 12));
+        assert!(new_line(&mut list));
+        assert_eq!(list.lines()[1], "  * [ ] ");
+
+        let mut list = textarea(&["> 9) nine"], (0, 9));
+        assert!(new_line(&mut list));
+        assert_eq!(list.lines()[1], "> 10) ");
+
+        let mut quote = textarea(&["> quoted"], (0, 8));
+        assert!(new_line(&mut quote));
+        assert_eq!(quote.lines(), ["> quoted", "> "]);
+
+        assert!(!new_line(&mut textarea(&["text"], (0, 4))));
+        // Before the marker Enter only breaks the line
+        assert!(!new_line(&mut textarea(&["- one"], (0, 1))));
+    }
+
+    #[test]
+    fn ending_lists() {
+        let mut list = textarea(&["- one", "- "], (1, 2));
+        assert!(new_line(&mut list));
+        assert_eq!(list.lines(), ["- one", ""]);
+        assert_eq!(list.cursor(), (1, 0));
+
+        // A nested empty item is outdented first
+        let mut list = textarea(&["- one", "  - "], (1, 4));
+        assert!(new_line(&mut list));
+        assert_eq!(list.lines(), ["- one", "- "]);
+
+        // The quote stays when its list ends
+        let mut list = textarea(&["> 1. one", "> 2. "], (1, 5));
+        assert!(new_line(&mut list));
+        assert_eq!(list.lines(), ["> 1. one", "> "]);
+    }
+
+    #[test]
+    fn indenting() {
+        let mut list = textarea(&["- one", "- two", "  more", "- three"], (1, 3));
+        list.set_tab_length(2);
+        assert!(indent(&mut list, true));
+        assert_eq!(list.lines(), ["- one", "  - two", "    more", "- three"]);
+        assert_eq!(list.cursor(), (1, 5));
+
+        assert!(indent(&mut list, false));
+        assert_eq!(list.lines(), ["- one", "- two", "  more", "- three"]);
+        assert_eq!(list.cursor(), (1, 3));
+        // A top level item has nothing to outdent, and other lines are not items
+        assert!(indent(&mut list, false));
+        assert_eq!(list.lines()[1], "- two");
+        assert!(!indent(&mut textarea(&["text"], (0, 0)), true));
+    }
+
+    #[test]
+    fn renumbering() {
+        let mut list = textarea(&["1. a", "1. b", "7. c", "", "4. d"], (0, 0));
+        renumber(&mut list);
+        assert_eq!(list.lines(), ["1. a", "2. b", "3. c", "", "4. d"]);
+
+        // A nested list counts from 1 and the outer one goes on after it
+        let mut list = textarea(&["3) a", "   5) b", "   9) c", "8) d", "- e"], (3, 3));
+        renumber(&mut list);
+        assert_eq!(list.lines(), ["3) a", "   1) b", "   2) c", "4) d", "- e"]);
+        assert_eq!(list.cursor(), (3, 3));
+
+        let mut list = textarea(&["9. a", "9. b"], (1, 4));
+        renumber(&mut list);
+        assert_eq!(list.lines(), ["9. a", "10. b"]);
+        assert_eq!(list.cursor(), (1, 5));
+    }
+}
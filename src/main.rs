@@ -1,7 +1,23 @@
 mod command;
+mod complete;
+mod config;
+mod date;
 mod editor;
 mod error;
+mod fold;
+mod history;
+mod list;
+mod markdown;
+mod note;
+mod pair;
+mod preview;
+mod replace;
+mod syntax;
+mod table;
+mod task;
+mod template;
 mod vault;
+mod view;
 mod vim;
 
 fn main() {
@@ -0,0 +1,471 @@
+use std::{ops::Range, sync::LazyLock};
+
+use ratatui::style::{Color, Modifier, Style};
+use regex::Regex;
+
+use crate::{
+    note,
+    syntax::{self, LANGUAGES},
+};
+
+static HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^ {0,3}(#{1,6})(?:\s|$)").unwrap());
+static QUOTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:\s*>)+").unwrap());
+/// A list marker, and the checkbox of a task after it
+static LIST: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*([-*+]|\d+[.)])(?:\s+|$)(\[.\](?:\s|$))?").unwrap());
+
+/// The first line of a callout, `> [!type]` with `-` or `+` when it folds
+static CALLOUT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*>\s*(\[!([\w-]+)\][-+]?)").unwrap());
+
+static CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`[^`]+`").unwrap());
+static BOLD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\*\*[^*\s](?:[^*]*[^*\s])?\*\*|__[^_\s](?:[^_]*[^_\s])?__").unwrap()
+});
+/// Single `*` or `_` emphasis, the marker not being part of a `**` or of a word
+static ITALIC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^*\w])(\*[^*\s](?:[^*]*[^*\s])?\*|_[^_\s](?:[^_]*[^_\s])?_)(?:$|[^*\w])")
+        .unwrap()
+});
+static STRIKE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"~~[^~]+~~").unwrap());
+static WIKILINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!?\[\[[^\]]+\]\]").unwrap());
+/// A Markdown link, with the text and the address
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!?(\[[^\]]*\])(\([^)]*\))").unwrap());
+
+pub const MARKUP: Style = Style::new().fg(Color::DarkGray);
+const HEADINGS: [Color; 6] = [
+    Color::Red,
+    Color::LightRed,
+    Color::LightYellow,
+    Color::LightGreen,
+    Color::LightCyan,
+    Color::LightBlue,
+];
+pub const QUOTE_MARKER: Style = Style::new().fg(Color::Blue);
+const QUOTE_TEXT: Style = Style::new().add_modifier(Modifier::ITALIC);
+pub const LIST_MARKER: Style = Style::new().fg(Color::LightBlue);
+pub const OPEN_TASK: Style = Style::new().fg(Color::Cyan);
+pub const DONE_TASK: Style = Style::new().fg(Color::Green);
+pub const DONE_TEXT: Style = Style::new()
+    .fg(Color::DarkGray)
+    .add_modifier(Modifier::CROSSED_OUT);
+pub const CODE_STYLE: Style = Style::new().fg(Color::Yellow);
+pub const LINK_STYLE: Style = Style::new()
+    .fg(Color::Blue)
+    .add_modifier(Modifier::UNDERLINED);
+pub const TAG_STYLE: Style = Style::new().fg(Color::Magenta);
+pub const FRONTMATTER_KEY: Style = Style::new().fg(Color::Cyan);
+
+/// The callout types of Obsidian and the colors it draws them in
+pub const CALLOUTS: [(&str, Color); 27] = [
+    ("note", Color::Blue),
+    ("abstract", Color::Cyan),
+    ("summary", Color::Cyan),
+    ("tldr", Color::Cyan),
+    ("info", Color::Blue),
+    ("todo", Color::Blue),
+    ("tip", Color::Cyan),
+    ("hint", Color::Cyan),
+    ("important", Color::Cyan),
+    ("success", Color::Green),
+    ("check", Color::Green),
+    ("done", Color::Green),
+    ("question", Color::Yellow),
+    ("help", Color::Yellow),
+    ("faq", Color::Yellow),
+    ("warning", Color::LightRed),
+    ("caution", Color::LightRed),
+    ("attention", Color::LightRed),
+    ("failure", Color::Red),
+    ("fail", Color::Red),
+    ("missing", Color::Red),
+    ("danger", Color::Red),
+    ("error", Color::Red),
+    ("bug", Color::Red),
+    ("example", Color::Magenta),
+    ("quote", Color::Gray),
+    ("cite", Color::Gray),
+];
+
+/// What a line is inside of, which decides how the line after it is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Block {
+    /// The first line of a note, where frontmatter may start
+    Start,
+    Text,
+    Frontmatter,
+    /// The lines of a callout after its first, drawn in the color of its type
+    Callout(Color),
+    /// A fenced code block, closed by at least as many of the same marker, and the
+    /// language its info string names
+    Fence {
+        marker: char,
+        len: usize,
+        language: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Highlighted {
+    text: String,
+    /// The block the line starts in
+    block: Block,
+    styles: Vec<Style>,
+    /// The block the next line starts in
+    next: Block,
+    /// The characters conceal mode hides
+    hidden: Vec<Range<usize>>,
+}
+
+/// The styles of the lines of a buffer, kept between renders so that only the lines that
+/// changed, or that a change moved into another block like an opened fence, are read again
+#[derive(Debug, Clone, Default)]
+pub struct Highlighter {
+    lines: Vec<Highlighted>,
+}
+
+impl Highlighter {
+    /// Bring the styles of the first `until` lines up to date
+    pub fn update(&mut self, lines: &[String], until: usize) {
+        self.lines.truncate(lines.len());
+
+        let mut block = Block::Start;
+        for (row, line) in lines.iter().enumerate().take(until) {
+            if let Some(highlighted) = self.lines.get(row)
+                && highlighted.block == block
+                && highlighted.text == *line
+            {
+                block = highlighted.next;
+                continue;
+            }
+
+            let (styles, next) = highlight_line(line, block);
+            // Only text is concealed, not code, frontmatter or the fences around code
+            let hidden = if matches!(block, Block::Start | Block::Text | Block::Callout(_))
+                && matches!(next, Block::Text | Block::Callout(_))
+            {
+                conceal(line)
+            } else {
+                Vec::new()
+            };
+            let highlighted = Highlighted {
+                text: line.clone(),
+                block,
+                styles,
+                next,
+                hidden,
+            };
+            match self.lines.get_mut(row) {
+                Some(old) => *old = highlighted,
+                None => self.lines.push(highlighted),
+            }
+            block = next;
+        }
+    }
+
+    /// The style of every character of a line, as of the last update
+    pub fn styles(&self, row: usize) -> &[Style] {
+        self.lines
+            .get(row)
+            .map_or(&[], |highlighted| &highlighted.styles)
+    }
+
+    /// The characters of a line conceal mode hides, as of the last update
+    pub fn hidden(&self, row: usize) -> &[Range<usize>] {
+        self.lines
+            .get(row)
+            .map_or(&[], |highlighted| &highlighted.hidden)
+    }
+}
+
+/// The style of every character of a line starting in `block`, and the block of the next line
+pub fn highlight_line(line: &str, block: Block) -> (Vec<Style>, Block) {
+    let mut styles = vec![Style::default(); line.chars().count()];
+    let all = 0..line.len();
+
+    match block {
+        Block::Frontmatter if matches!(line.trim_end(), "---" | "...") => {
+            paint(&mut styles, line, all, MARKUP);
+            (styles, Block::Text)
+        }
+        Block::Frontmatter => {
+            if !line.starts_with([' ', '-'])
+                && let Some(colon) = line.find(':')
+            {
+                paint(&mut styles, line, 0..colon, FRONTMATTER_KEY);
+            }
+            (styles, Block::Frontmatter)
+        }
+        Block::Fence {
+            marker,
+            len,
+            language,
+        } => match fence(line) {
+            Some((closing, closing_len, info))
+                if closing == marker && closing_len >= len && info.is_empty() =>
+            {
+                paint(&mut styles, line, all, MARKUP);
+                (styles, Block::Text)
+            }
+            // Code in a language that is not known is only told apart from the text around it
+            _ => {
+                match language {
+                    Some(language) => syntax::highlight(line, &LANGUAGES[language], &mut styles),
+                    None => paint(&mut styles, line, all, CODE_STYLE),
+                }
+                (styles, block)
+            }
+        },
+        Block::Start if line.trim_end() == "---" => {
+            paint(&mut styles, line, all, MARKUP);
+            (styles, Block::Frontmatter)
+        }
+        Block::Callout(color) if line.trim_start().starts_with('>') => {
+            highlight_text(line, &mut styles);
+            let quote = QUOTE.find(line).unwrap().range();
+            paint(&mut styles, line, quote, Style::new().fg(color));
+            (styles, block)
+        }
+        Block::Callout(_) => highlight_line(line, Block::Text),
+        Block::Start | Block::Text if CALLOUT.is_match(line) => {
+            let callout = CALLOUT.captures(line).unwrap();
+            let color = callout_color(&callout[2]);
+            highlight_text(line, &mut styles);
+            let title = Style::new().fg(color).add_modifier(Modifier::BOLD);
+            paint(&mut styles, line, 0..line.len(), Style::new().fg(color));
+            paint(
+                &mut styles,
+                line,
+                callout.get(1).unwrap().start()..line.len(),
+                title,
+            );
+            (styles, Block::Callout(color))
+        }
+        Block::Start | Block::Text => match fence(line) {
+            Some((marker, len, info)) => {
+                paint(&mut styles, line, all, MARKUP);
+                let language = syntax::language(info);
+                (
+                    styles,
+                    Block::Fence {
+                        marker,
+                        len,
+                        language,
+                    },
+                )
+            }
+            None => {
+                highlight_text(line, &mut styles);
+                (styles, Block::Text)
+            }
+        },
+    }
+}
+
+/// The marker of a fence opening or closing a code block, how long it is, and its info string
+pub fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start();
+    let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    let info = trimmed[len..].trim();
+
+    (len >= 3 && !(marker == '`' && info.contains('`'))).then_some((marker, len, info))
+}
+
+/// The color of a callout type, that of a note for types Obsidian does not know
+pub fn callout_color(kind: &str) -> Color {
+    CALLOUTS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(kind))
+        .map_or(Color::Blue, |(_, color)| *color)
+}
+
+/// The style of a heading of a level from 1 to 6
+pub fn heading_style(level: usize) -> Style {
+    Style::new()
+        .fg(HEADINGS[level.clamp(1, 6) - 1])
+        .add_modifier(Modifier::BOLD)
+}
+
+/// The level of a heading, from 1 for `#` to 6
+pub fn heading_level(line: &str) -> Option<usize> {
+    HEADING.captures(line).map(|heading| heading[1].len())
+}
+
+pub fn is_list_item(line: &str) -> bool {
+    LIST.is_match(line)
+}
+
+/// A line of `---`, `***` or `___`, spaces allowed in between
+pub fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|c| *c == marks[0])
+}
+
+fn highlight_text(line: &str, styles: &mut [Style]) {
+    if is_rule(line) {
+        paint(styles, line, 0..line.len(), MARKUP);
+        return;
+    }
+
+    // Quotes may hold any other block, so what follows their `>` is read like a line
+    let mut start = 0;
+    if let Some(quote) = QUOTE.find(line) {
+        paint(styles, line, quote.range(), QUOTE_MARKER);
+        paint(styles, line, quote.end()..line.len(), QUOTE_TEXT);
+        start = quote.end();
+    }
+    let rest = &line[start..];
+
+    if let Some(heading) = HEADING.captures(rest) {
+        paint(
+            styles,
+            line,
+            start..line.len(),
+            heading_style(heading[1].len()),
+        );
+    } else if let Some(list) = LIST.captures(rest) {
+        let marker = list.get(1).unwrap().range();
+        paint(
+            styles,
+            line,
+            start + marker.start..start + marker.end,
+            LIST_MARKER,
+        );
+        if let Some(checkbox) = list.get(2) {
+            let checkbox_text = checkbox.as_str().trim_end();
+            let done = !checkbox_text.starts_with("[ ]");
+            let box_end = start + checkbox.start() + checkbox_text.len();
+            let (box_style, text_style) = if done {
+                (DONE_TASK, DONE_TEXT)
+            } else {
+                (OPEN_TASK, Style::default())
+            };
+            paint(styles, line, start + checkbox.start()..box_end, box_style);
+            paint(styles, line, box_end..line.len(), text_style);
+        }
+    }
+
+    highlight_inline(line, styles);
+}
+
+/// Emphasis, links and tags, and code spans that show their text as it is
+fn highlight_inline(line: &str, styles: &mut [Style]) {
+    for bold in BOLD.find_iter(line) {
+        paint(
+            styles,
+            line,
+            bold.range(),
+            Style::new().add_modifier(Modifier::BOLD),
+        );
+    }
+    for italic in ITALIC.captures_iter(line) {
+        let range = italic.get(1).unwrap().range();
+        paint(
+            styles,
+            line,
+            range,
+            Style::new().add_modifier(Modifier::ITALIC),
+        );
+    }
+    for strike in STRIKE.find_iter(line) {
+        let style = Style::new().add_modifier(Modifier::CROSSED_OUT);
+        paint(styles, line, strike.range(), style);
+    }
+    for link in WIKILINK.find_iter(line) {
+        paint(styles, line, link.range(), LINK_STYLE);
+    }
+    for link in LINK.captures_iter(line) {
+        paint(styles, line, link.get(1).unwrap().range(), LINK_STYLE);
+        paint(styles, line, link.get(2).unwrap().range(), MARKUP);
+    }
+    for tag in note::tag_ranges(line) {
+        paint(styles, line, tag, TAG_STYLE);
+    }
+
+    for code in CODE.find_iter(line) {
+        let range = char_range(line, code.range());
+        styles[range].fill(CODE_STYLE);
+    }
+}
+
+/// The characters of the markup that conceal mode hides: the brackets of wikilinks and the
+/// target of those with an alias, the brackets and address of links, and emphasis markers.
+/// Code spans are left as they are, and so are embeds for they show the embedded note
+fn conceal(line: &str) -> Vec<Range<usize>> {
+    let code: Vec<Range<usize>> = CODE.find_iter(line).map(|code| code.range()).collect();
+    let mut hidden = Vec::new();
+    let mut hide = |range: Range<usize>, markers: &[Range<usize>]| {
+        if !code
+            .iter()
+            .any(|code| code.start < range.end && range.start < code.end)
+        {
+            hidden.extend(
+                markers
+                    .iter()
+                    .map(|marker| char_range(line, marker.clone())),
+            );
+        }
+    };
+
+    for link in WIKILINK.find_iter(line) {
+        let range = link.range();
+        if link.as_str().starts_with('!') {
+            continue;
+        }
+        let target_end = link
+            .as_str()
+            .find('|')
+            .map_or(range.start + 2, |bar| range.start + bar + 1);
+        hide(
+            range.clone(),
+            &[range.start..target_end, range.end - 2..range.end],
+        );
+    }
+    for link in LINK.captures_iter(line) {
+        let (whole, text, address) = (
+            link.get(0).unwrap(),
+            link.get(1).unwrap(),
+            link.get(2).unwrap(),
+        );
+        if whole.as_str().starts_with('!') {
+            continue;
+        }
+        hide(
+            whole.range(),
+            &[
+                text.start()..text.start() + 1,
+                text.end() - 1..address.end(),
+            ],
+        );
+    }
+    for emphasis in BOLD.find_iter(line).chain(STRIKE.find_iter(line)) {
+        let range = emphasis.range();
+        hide(
+            range.clone(),
+            &[range.start..range.start + 2, range.end - 2..range.end],
+        );
+    }
+    for italic in ITALIC.captures_iter(line) {
+        let range = italic.get(1).unwrap().range();
+        hide(
+            range.clone(),
+            &[range.start..range.start + 1, range.end - 1..range.end],
+        );
+    }
+
+    hidden
+}
+
+/// The characters of a byte range
+fn char_range(line: &str, range: Range<usize>) -> Range<usize> {
+    let start = line[..range.start].chars().count();
+    start..start + line[range].chars().count()
+}
+
+/// Patch the style of the characters of a byte range
+fn paint(styles: &mut [Style], line: &str, range: Range<usize>, style: Style) {
+    for cell in &mut styles[char_range(line, range)] {
+        *cell = cell.patch(style);
+    }
+}
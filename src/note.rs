@@ -0,0 +1,140 @@
+use std::{ops::Range, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::markdown;
+
+/// An Obsidian tag: `#` after whitespace or the start of a line, not only digits
+static TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)(?:^|\s)(#[\w/-]*[\p{L}_][\w/-]*)").unwrap());
+
+/// An embed, `![[note]]` or `![[note#section]]`, and what it embeds
+pub static EMBED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!\[\[([^\]|]+)(?:\|[^\]]*)?\]\]").unwrap());
+/// A heading, with its level and text
+static HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}(#{1,6})\s+(.*?)(?:\s+#+)?\s*$").unwrap());
+
+/// The tags written in the text of a note, including their `#`
+pub fn tags(text: &str) -> impl Iterator<Item = &str> {
+    tag_ranges(text).map(|range| &text[range])
+}
+
+/// Where the tags of a text are, as byte ranges
+pub fn tag_ranges(text: &str) -> impl Iterator<Item = Range<usize>> {
+    TAG.captures_iter(text)
+        .map(|captures| captures.get(1).unwrap().range())
+}
+
+/// The top level fields of a note's YAML frontmatter, with their value or list of values.
+/// Only the simple YAML notes use is understood: `key: value`, `key: [a, b]` and `- item` lists
+pub fn frontmatter(text: &str) -> Vec<(String, Vec<String>)> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return Vec::new();
+    }
+
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+    for line in lines {
+        if line.trim_end() == "---" {
+            return fields;
+        }
+
+        if let Some(item) = line.trim_start().strip_prefix("- ")
+            && line.starts_with([' ', '-'])
+        {
+            if let Some((_, values)) = fields.last_mut() {
+                values.push(unquote(item));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if line.starts_with(' ') {
+            continue;
+        }
+        let value = value.trim();
+        let values = match value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+        {
+            Some(list) => list
+                .split(',')
+                .map(unquote)
+                .filter(|item| !item.is_empty())
+                .collect(),
+            None if value.is_empty() => Vec::new(),
+            None => vec![unquote(value)],
+        };
+        fields.push((key.trim().to_string(), values));
+    }
+
+    // Without a closing `---` there is no frontmatter
+    Vec::new()
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}
+
+/// The headings of a note, with their row, level and text
+pub fn headings(lines: &[String]) -> Vec<(usize, usize, &str)> {
+    // Lines of code like `# comment` are no headings
+    let mut in_code = false;
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            if markdown::fence(line).is_some() {
+                in_code = !in_code;
+            }
+            let heading = HEADING.captures(line).filter(|_| !in_code)?;
+            Some((row, heading[1].len(), heading.get(2).unwrap().as_str()))
+        })
+        .collect()
+}
+
+/// The other names of a note, from the `aliases` of its frontmatter
+pub fn aliases(text: &str) -> Vec<String> {
+    frontmatter(text)
+        .into_iter()
+        .filter(|(key, _)| key == "aliases" || key == "alias")
+        .flat_map(|(_, values)| values)
+        .collect()
+}
+
+/// The rows of a section of a note, as a link like `note#section` names it: a heading and
+/// the lines up to the next heading of the same or a higher level, or with `^id` the line
+/// ending with that block id
+pub fn section(lines: &[String], section: &str) -> Option<Range<usize>> {
+    if section.starts_with('^') {
+        let row = lines.iter().position(|line| {
+            line.trim_end()
+                .strip_suffix(section)
+                .is_some_and(|rest| rest.is_empty() || rest.ends_with(char::is_whitespace))
+        })?;
+        return Some(row..row + 1);
+    }
+
+    let headings = headings(lines);
+    let index = headings
+        .iter()
+        .position(|(_, _, text)| text.eq_ignore_ascii_case(section.trim()))?;
+    let (start, level, _) = headings[index];
+    let end = headings[index + 1..]
+        .iter()
+        .find(|(_, other, _)| *other <= level)
+        .map_or(lines.len(), |(row, _, _)| *row);
+    Some(start..end)
+}
@@ -0,0 +1,96 @@
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::view::jump;
+
+/// The characters typed in pairs, and the one closing each
+const PAIRS: [(char, char); 5] = [('(', ')'), ('"', '"'), ('`', '`'), ('*', '*'), ('_', '_')];
+
+/// The pairs Backspace deletes together when the cursor is between them
+const DELETED: [(char, char); 7] = [
+    ('(', ')'),
+    ('[', ']'),
+    ('"', '"'),
+    ('`', '`'),
+    ('*', '*'),
+    ('_', '_'),
+    ('{', '}'),
+];
+
+/// The characters before and after the cursor
+fn around(textarea: &TextArea) -> (String, String) {
+    let (row, col) = textarea.cursor();
+    let line = &textarea.lines()[row];
+    (
+        line.chars().take(col).collect(),
+        line.chars().skip(col).collect(),
+    )
+}
+
+/// Type a character in Insert mode, closing what it opens. A closing character typed right
+/// before the same one moves over it, `[[` gets its `]]` and the third backtick at the start
+/// of a line closes a code block
+pub fn insert(textarea: &mut TextArea, c: char) {
+    let (before, after) = around(textarea);
+    let previous = before.chars().next_back();
+    let next = after.chars().next();
+
+    if c == '[' && previous == Some('[') {
+        textarea.insert_str("[]]");
+        textarea.move_cursor(CursorMove::Back);
+        textarea.move_cursor(CursorMove::Back);
+        return;
+    }
+
+    if c == '`' && before.trim_start() == "``" && after.is_empty() {
+        let (row, col) = textarea.cursor();
+        let indent = &before[..before.len() - 2];
+        textarea.insert_str(format!("`\n{indent}```"));
+        jump(textarea, (row, col + 1));
+        return;
+    }
+
+    let closes = next == Some(c) && (c == ']' || PAIRS.iter().any(|(_, close)| *close == c));
+    // Between `*` and `*`, another one makes bold markers rather than leaving them
+    let nests = matches!(c, '*' | '_') && previous == Some(c);
+    if closes && !nests {
+        textarea.move_cursor(CursorMove::Forward);
+        return;
+    }
+
+    let Some((_, close)) = PAIRS.iter().find(|(open, _)| *open == c) else {
+        textarea.insert_char(c);
+        return;
+    };
+    let free_after =
+        next.is_none_or(|next| next.is_whitespace() || ")]}".contains(next) || next == c);
+    // Not in a word, nor after a lone marker of the same kind
+    let free_before = c == '('
+        || match previous {
+            Some(previous) if previous == c => next == Some(c),
+            Some(previous) => !previous.is_alphanumeric(),
+            None => true,
+        };
+    // A `*` or `_` starting a line is a list item or a rule
+    let starts_line = matches!(c, '*' | '_') && before.trim().is_empty();
+    textarea.insert_char(c);
+    if free_after && free_before && !starts_line {
+        textarea.insert_char(*close);
+        textarea.move_cursor(CursorMove::Back);
+    }
+}
+
+/// Backspace in Insert mode between an opening character and the one closing it deletes
+/// both. Returns false when the cursor is not between a pair
+pub fn delete(textarea: &mut TextArea) -> bool {
+    let (before, after) = around(textarea);
+    let pair = (before.chars().next_back(), after.chars().next());
+    if !DELETED
+        .iter()
+        .any(|(open, close)| pair == (Some(*open), Some(*close)))
+    {
+        return false;
+    }
+    textarea.delete_next_char();
+    textarea.delete_char();
+    true
+}
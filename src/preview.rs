@@ -0,0 +1,664 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    markdown::{
+        self, Block, CODE_STYLE, DONE_TASK, DONE_TEXT, FRONTMATTER_KEY, LINK_STYLE, LIST_MARKER,
+        MARKUP, OPEN_TASK, QUOTE_MARKER, TAG_STYLE,
+    },
+    note, table,
+};
+
+static HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}(#{1,6})(?:\s+(.*?))?(?:\s+#+)?\s*$").unwrap());
+/// The indent, marker and checkbox of a list item, and its text
+static LIST: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*)([-*+]|\d+[.)])(?:\s+\[(.)\])?(?:\s+(.*))?$").unwrap());
+/// The first line of a callout, `[!type]` with `-` or `+` when it folds, and its title
+static CALLOUT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[!([\w-]+)\]([-+]?)\s*(.*)$").unwrap());
+
+/// Code spans, links and emphasis, which the preview shows without their markup
+static INLINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"`(?P<code>[^`]+)`",
+        r"|!?\[\[(?P<target>[^\]|]+)(?:\|(?P<alias>[^\]]*))?\]\]",
+        r"|!?\[(?P<text>[^\]]*)\]\((?P<url>[^)]*)\)",
+        r"|\*\*(?P<bold>[^*\s](?:[^*]*[^*\s])?)\*\*|__(?P<bold_>[^_\s](?:[^_]*[^_\s])?)__",
+        r"|~~(?P<strike>[^~]+)~~",
+        r"|\*(?P<italic>[^*\s](?:[^*]*[^*\s])?)\*|\b_(?P<italic_>[^_\s](?:[^_]*[^_\s])?)_\b",
+    ))
+    .unwrap()
+});
+
+/// Narrower than this, boxes and indents would leave no room for text
+const MIN_WIDTH: usize = 12;
+
+/// Text of one style, part of the link of that index when it has one
+#[derive(Debug, Clone)]
+pub struct Piece {
+    pub text: String,
+    pub style: Style,
+    pub link: Option<usize>,
+}
+
+impl Piece {
+    fn new(text: impl Into<String>, style: Style) -> Self {
+        Self {
+            text: text.into(),
+            style,
+            link: None,
+        }
+    }
+}
+
+/// A link the preview can follow: a note like `note#heading`, or a Markdown link's address
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub target: String,
+    /// The first line it is drawn on
+    pub row: usize,
+}
+
+/// A note rendered for reading
+#[derive(Debug, Clone, Default)]
+pub struct Preview {
+    pub lines: Vec<Vec<Piece>>,
+    pub links: Vec<Link>,
+}
+
+impl Preview {
+    /// The lines to draw, the link at `selected` standing out
+    pub fn text(&self, selected: Option<usize>) -> Vec<Line<'static>> {
+        self.lines
+            .iter()
+            .map(|pieces| {
+                Line::from(
+                    pieces
+                        .iter()
+                        .map(|piece| {
+                            let mut style = piece.style;
+                            if piece.link.is_some() && piece.link == selected {
+                                style = style.add_modifier(Modifier::REVERSED);
+                            }
+                            Span::styled(piece.text.clone(), style)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// The path and lines of the note a link like `note` names, when there is one
+pub type Notes<'a> = dyn Fn(&str) -> Option<(PathBuf, Vec<String>)> + 'a;
+
+/// What rendering carries from block to block
+struct Context<'a> {
+    links: Vec<Link>,
+    notes: &'a Notes<'a>,
+    /// The note being rendered and the notes it embeds down to the current one, for an
+    /// embed of any of them would never end
+    embedding: Vec<PathBuf>,
+    /// Show the content of folded callouts
+    unfold: bool,
+}
+
+/// Render the lines of the note at `path` to fit in `width` columns, reading the notes it
+/// embeds from `notes`. Folded callouts show their content only with `unfold`
+pub fn render(path: &Path, lines: &[String], width: usize, notes: &Notes, unfold: bool) -> Preview {
+    let mut context = Context {
+        links: Vec::new(),
+        notes,
+        embedding: vec![path.to_path_buf()],
+        unfold,
+    };
+    let lines = blocks(lines, width, &mut context);
+    let mut links = context.links;
+
+    for (row, line) in lines.iter().enumerate() {
+        for piece in line {
+            if let Some(link) = piece.link
+                && links[link].row == usize::MAX
+            {
+                links[link].row = row;
+            }
+        }
+    }
+    Preview { lines, links }
+}
+
+fn blocks(lines: &[String], width: usize, context: &mut Context) -> Vec<Vec<Piece>> {
+    let width = width.max(MIN_WIDTH);
+    let mut out: Vec<Vec<Piece>> = Vec::new();
+    let mut row = 0;
+
+    if let Some(end) = frontmatter_end(lines) {
+        let fields = lines[1..end - 1]
+            .iter()
+            .flat_map(|line| {
+                let pieces = match line.split_once(':') {
+                    Some((key, value)) if !line.starts_with([' ', '-']) => vec![
+                        Piece::new(key, FRONTMATTER_KEY),
+                        Piece::new(format!(":{}", value), MARKUP),
+                    ],
+                    _ => vec![Piece::new(line.as_str(), MARKUP)],
+                };
+                wrap(pieces, width - 4, Vec::new(), Vec::new())
+            })
+            .collect();
+        out.extend(boxed(
+            vec![Piece::new("Properties", MARKUP)],
+            fields,
+            width,
+            MARKUP,
+        ));
+        row = end;
+    }
+
+    while row < lines.len() {
+        let line = &lines[row];
+        let trimmed = line.trim_start();
+
+        if let Some((_, _, info)) = markdown::fence(line) {
+            let (_, mut block) = markdown::highlight_line(line, Block::Text);
+            let title = info.split_whitespace().next().unwrap_or("code");
+            let mut code = Vec::new();
+            row += 1;
+            while row < lines.len() {
+                let (styles, next) = markdown::highlight_line(&lines[row], block);
+                row += 1;
+                if next == Block::Text {
+                    break;
+                }
+                code.extend(wrap(
+                    styled(&lines[row - 1], &styles),
+                    width - 4,
+                    Vec::new(),
+                    Vec::new(),
+                ));
+                block = next;
+            }
+            out.extend(boxed(vec![Piece::new(title, MARKUP)], code, width, MARKUP));
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            let mut inner = Vec::new();
+            while let Some(quoted) = lines
+                .get(row)
+                .and_then(|line| line.trim_start().strip_prefix('>'))
+            {
+                inner.push(quoted.strip_prefix(' ').unwrap_or(quoted).to_string());
+                row += 1;
+            }
+            out.extend(quote(&inner, width, context));
+            continue;
+        }
+
+        if let Some(embed) = note::EMBED
+            .captures(line.trim())
+            .filter(|embed| embed[0].len() == line.trim().len())
+        {
+            row += 1;
+            out.extend(embed_block(&embed[1], width, context));
+            continue;
+        }
+
+        if trimmed.starts_with('|')
+            && lines
+                .get(row + 1)
+                .is_some_and(|next| table::is_separator(&table::cells(next)))
+        {
+            let start = row;
+            while lines
+                .get(row)
+                .is_some_and(|line| line.trim_start().starts_with('|'))
+            {
+                row += 1;
+            }
+            out.extend(table(&lines[start..row], context));
+            continue;
+        }
+
+        row += 1;
+        if trimmed.is_empty() {
+            // Like a rendered page, blank lines only ever separate blocks
+            if out.last().is_some_and(|last| !last.is_empty()) {
+                out.push(Vec::new());
+            }
+        } else if markdown::is_rule(line) {
+            out.push(vec![Piece::new("─".repeat(width), MARKUP)]);
+        } else if let Some(heading) = HEADING.captures(line) {
+            let style = markdown::heading_style(heading[1].len());
+            let text = heading.get(2).map_or("", |text| text.as_str());
+            out.extend(wrap(
+                inline(text, style, &mut context.links),
+                width,
+                Vec::new(),
+                Vec::new(),
+            ));
+        } else if let Some(item) = LIST.captures(line) {
+            let indent = " ".repeat(item[1].replace('\t', "    ").len());
+            let marker = &item[2];
+            let text = item.get(4).map_or("", |text| text.as_str());
+            let (bullet, style) = match item.get(3).map(|checkbox| checkbox.as_str()) {
+                Some(" ") => ("☐".to_string(), OPEN_TASK),
+                Some(_) => ("☑".to_string(), DONE_TASK),
+                None if marker.starts_with(|c: char| c.is_ascii_digit()) => {
+                    (marker.to_string(), LIST_MARKER)
+                }
+                None => ("•".to_string(), LIST_MARKER),
+            };
+            let text_style = if style == DONE_TASK {
+                DONE_TEXT
+            } else {
+                Style::default()
+            };
+            let hanging = " ".repeat(indent.len() + bullet.width() + 1);
+            out.extend(wrap(
+                inline(text, text_style, &mut context.links),
+                width,
+                vec![
+                    Piece::new(indent, Style::default()),
+                    Piece::new(bullet + " ", style),
+                ],
+                vec![Piece::new(hanging, Style::default())],
+            ));
+        } else {
+            out.extend(wrap(
+                inline(trimmed, Style::default(), &mut context.links),
+                width,
+                Vec::new(),
+                Vec::new(),
+            ));
+        }
+    }
+
+    while out.last().is_some_and(|last| last.is_empty()) {
+        out.pop();
+    }
+    out
+}
+
+/// The row after the frontmatter of a note, when it has one
+fn frontmatter_end(lines: &[String]) -> Option<usize> {
+    if lines.first()?.trim_end() != "---" {
+        return None;
+    }
+    lines[1..]
+        .iter()
+        .position(|line| matches!(line.trim_end(), "---" | "..."))
+        .map(|end| end + 2)
+}
+
+/// An embed on a line of its own: the note, or its section, in a box titled with a link to it.
+/// Embeds of other files than notes, like images, only show their link
+fn embed_block(target: &str, width: usize, context: &mut Context) -> Vec<Vec<Piece>> {
+    let (name, section) = match target.split_once('#') {
+        Some((name, section)) => (name.trim(), Some(section.trim())),
+        None => (target.trim(), None),
+    };
+    let title = vec![link(
+        target.replace('#', " > "),
+        target,
+        Style::default(),
+        &mut context.links,
+    )];
+    if Path::new(name)
+        .extension()
+        .is_some_and(|extension| extension != "md")
+    {
+        return vec![title];
+    }
+
+    let message = |text: String| vec![vec![Piece::new(text, MARKUP)]];
+    let inner = match (context.notes)(name) {
+        None => message(format!("No note named {}", name)),
+        Some((path, _)) if context.embedding.contains(&path) => {
+            message(format!("{} is not shown again inside itself", name))
+        }
+        Some((path, lines)) => {
+            let range = match section {
+                Some(section) => note::section(&lines, section),
+                None => Some(frontmatter_end(&lines).unwrap_or(0)..lines.len()),
+            };
+            match range {
+                Some(range) => {
+                    context.embedding.push(path);
+                    let inner = blocks(&lines[range], width - 4, context);
+                    context.embedding.pop();
+                    inner
+                }
+                None => message(format!("No section {} in {}", section.unwrap(), name)),
+            }
+        }
+    };
+    boxed(title, inner, width, MARKUP)
+}
+
+/// A block quote, or a callout titled by its type or its own title, in a box
+fn quote(inner: &[String], width: usize, context: &mut Context) -> Vec<Vec<Piece>> {
+    let callout = inner.first().and_then(|first| CALLOUT.captures(first));
+    let Some(callout) = callout else {
+        return boxed(
+            Vec::new(),
+            blocks(inner, width - 4, context),
+            width,
+            QUOTE_MARKER,
+        );
+    };
+
+    let kind = &callout[1];
+    let color = Style::new().fg(markdown::callout_color(kind));
+    let title = match callout[3].trim() {
+        "" => {
+            let mut chars = kind.chars();
+            chars
+                .next()
+                .map(|first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+        title => title.to_string(),
+    };
+    let mut title = inline(
+        &title,
+        color.add_modifier(Modifier::BOLD),
+        &mut context.links,
+    );
+
+    // A callout folded with `-` shows only its title until folds are opened
+    let folded = &callout[2] == "-" && !context.unfold;
+    match &callout[2] {
+        "" => (),
+        _ if folded => title.insert(0, Piece::new("▸ ", color)),
+        _ => title.insert(0, Piece::new("▾ ", color)),
+    }
+    let inner = if folded {
+        Vec::new()
+    } else {
+        blocks(&inner[1..], width - 4, context)
+    };
+    boxed(title, inner, width, color)
+}
+
+/// Lines framed in a box `width` wide, with a title in its top border
+fn boxed(
+    title: Vec<Piece>,
+    inner: Vec<Vec<Piece>>,
+    width: usize,
+    border: Style,
+) -> Vec<Vec<Piece>> {
+    let mut top = vec![Piece::new("╭─", border)];
+    if !title.is_empty() {
+        // A title too long for the border is cut short
+        let mut room = width.saturating_sub(6);
+        top.push(Piece::new(" ", border));
+        for mut piece in title {
+            if room == 0 {
+                break;
+            }
+            piece.text = truncate(&piece.text, room);
+            room -= piece.text.width();
+            top.push(piece);
+        }
+        top.push(Piece::new(" ", border));
+    }
+    let used = line_width(&top);
+    top.push(Piece::new(
+        "─".repeat(width.saturating_sub(used + 1)) + "╮",
+        border,
+    ));
+
+    let mut out = vec![top];
+    for mut line in inner {
+        let pad = (width - 4).saturating_sub(line_width(&line));
+        line.insert(0, Piece::new("│ ", border));
+        line.push(Piece::new(" ".repeat(pad), Style::default()));
+        line.push(Piece::new(" │", border));
+        out.push(line);
+    }
+    out.push(vec![Piece::new(
+        format!("╰{}╯", "─".repeat(width - 2)),
+        border,
+    )]);
+    out
+}
+
+/// A table with its columns aligned as its separator row says, and its header in bold
+fn table(rows: &[String], context: &mut Context) -> Vec<Vec<Piece>> {
+    let separator = table::cells(&rows[1]);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rendered: Vec<Vec<Vec<Piece>>> = rows
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != 1)
+        .map(|(index, row)| {
+            let style = if index == 0 { bold } else { Style::default() };
+            table::cells(row)
+                .iter()
+                .map(|cell| inline(&cell.replace("\\|", "|"), style, &mut context.links))
+                .collect()
+        })
+        .collect();
+
+    let columns = rendered.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rendered
+                .iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| line_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let border = |left: &str, middle: &str, right: &str| {
+        let parts: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+        vec![Piece::new(
+            format!("{}{}{}", left, parts.join(middle), right),
+            MARKUP,
+        )]
+    };
+
+    let mut out = vec![border("┌", "┬", "┐")];
+    for (index, row) in rendered.into_iter().enumerate() {
+        let mut line = Vec::new();
+        for (column, width) in widths.iter().enumerate() {
+            let cell = row.get(column).cloned().unwrap_or_default();
+            let pad = width - line_width(&cell);
+            let alignment = separator.get(column).map_or("", |cell| cell.as_str());
+            let (before, after) = match (alignment.starts_with(':'), alignment.ends_with(':')) {
+                (true, true) => (pad / 2, pad - pad / 2),
+                (false, true) => (pad, 0),
+                _ => (0, pad),
+            };
+            line.push(Piece::new(if column == 0 { "│ " } else { " │ " }, MARKUP));
+            line.push(Piece::new(" ".repeat(before), Style::default()));
+            line.extend(cell);
+            line.push(Piece::new(" ".repeat(after), Style::default()));
+        }
+        line.push(Piece::new(" │", MARKUP));
+        out.push(line);
+        if index == 0 {
+            out.push(border("├", "┼", "┤"));
+        }
+    }
+    out.push(border("└", "┴", "┘"));
+    out
+}
+
+/// The text of a line without its inline markup, links added to `links`
+fn inline(text: &str, base: Style, links: &mut Vec<Link>) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut last = 0;
+
+    for captures in INLINE.captures_iter(text) {
+        let whole = captures.get(0).unwrap();
+        plain(&text[last..whole.start()], base, &mut pieces);
+        last = whole.end();
+
+        let emphasis = |name: &str| captures.name(name).map(|inner| inner.as_str());
+        if let Some(code) = captures.name("code") {
+            pieces.push(Piece::new(code.as_str(), base.patch(CODE_STYLE)));
+        } else if let Some(target) = captures.name("target") {
+            let display = match captures.name("alias") {
+                Some(alias) => alias.as_str().to_string(),
+                None => target.as_str().replace('#', " > "),
+            };
+            pieces.push(link(display, target.as_str(), base, links));
+        } else if let Some(url) = captures.name("url") {
+            pieces.push(link(&captures["text"], url.as_str(), base, links));
+        } else if let Some(inner) = emphasis("bold").or(emphasis("bold_")) {
+            pieces.extend(inline(inner, base.add_modifier(Modifier::BOLD), links));
+        } else if let Some(inner) = emphasis("italic").or(emphasis("italic_")) {
+            pieces.extend(inline(inner, base.add_modifier(Modifier::ITALIC), links));
+        } else if let Some(inner) = emphasis("strike") {
+            pieces.extend(inline(
+                inner,
+                base.add_modifier(Modifier::CROSSED_OUT),
+                links,
+            ));
+        }
+    }
+    plain(&text[last..], base, &mut pieces);
+    pieces
+}
+
+fn link(display: impl Into<String>, target: &str, base: Style, links: &mut Vec<Link>) -> Piece {
+    links.push(Link {
+        target: target.trim().to_string(),
+        row: usize::MAX,
+    });
+    Piece {
+        text: display.into(),
+        style: base.patch(LINK_STYLE),
+        link: Some(links.len() - 1),
+    }
+}
+
+/// Text with nothing but tags to style
+fn plain(text: &str, base: Style, pieces: &mut Vec<Piece>) {
+    let mut last = 0;
+    for tag in note::tag_ranges(text) {
+        if tag.start > last {
+            pieces.push(Piece::new(&text[last..tag.start], base));
+        }
+        pieces.push(Piece::new(&text[tag.clone()], base.patch(TAG_STYLE)));
+        last = tag.end;
+    }
+    if last < text.len() {
+        pieces.push(Piece::new(&text[last..], base));
+    }
+}
+
+/// A line split into runs of the same style
+fn styled(line: &str, styles: &[Style]) -> Vec<Piece> {
+    let mut pieces: Vec<Piece> = Vec::new();
+    for (c, style) in line.chars().zip(styles) {
+        match pieces.last_mut() {
+            Some(last) if last.style == *style => last.text.push(c),
+            _ => pieces.push(Piece::new(c, *style)),
+        }
+    }
+    pieces
+}
+
+fn line_width(pieces: &[Piece]) -> usize {
+    pieces.iter().map(|piece| piece.text.width()).sum()
+}
+
+/// The start of a text no wider than `width`
+fn truncate(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.chars()
+        .take_while(|c| {
+            used += c.to_string().width();
+            used <= width
+        })
+        .collect()
+}
+
+/// Break pieces into lines `width` wide at whitespace, or anywhere in a word longer than a
+/// line. The first line starts with `first`, the others with `rest`
+fn wrap(pieces: Vec<Piece>, width: usize, first: Vec<Piece>, rest: Vec<Piece>) -> Vec<Vec<Piece>> {
+    let mut lines = Vec::new();
+    let mut line = first;
+    let mut used = line_width(&line);
+    let mut fresh = true;
+
+    for piece in pieces {
+        for word in words(&piece.text) {
+            let blank = word.trim().is_empty();
+            if used + word.width() > width && !fresh {
+                lines.push(std::mem::replace(&mut line, rest.clone()));
+                used = line_width(&line);
+                fresh = true;
+                if blank {
+                    continue;
+                }
+            }
+
+            let mut word = word;
+            while used + word.width() > width {
+                let head = truncate(word, width.saturating_sub(used));
+                // Always take a character, so that a line too narrow for one still ends
+                let head = if head.is_empty() {
+                    word.chars().next().unwrap().to_string()
+                } else {
+                    head
+                };
+                line.push(Piece {
+                    text: head.clone(),
+                    ..piece.clone()
+                });
+                lines.push(std::mem::replace(&mut line, rest.clone()));
+                used = line_width(&line);
+                word = &word[head.len()..];
+            }
+            if !word.is_empty() {
+                used += word.width();
+                line.push(Piece {
+                    text: word.to_string(),
+                    ..piece.clone()
+                });
+                fresh = false;
+            }
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// A text split into runs of whitespace and of anything else
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if let Some(&(next_index, next)) = chars.peek()
+            && next.is_whitespace() != c.is_whitespace()
+        {
+            words.push(&text[start..next_index]);
+            start = next_index;
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
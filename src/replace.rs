@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+/// A run of neighbouring lines of one note that a vault-wide replace changes
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub path: PathBuf,
+    /// The row of the first changed line
+    pub start: usize,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+    pub accepted: bool,
+}
+
+/// Every hunk that replacing the first match of `regex` on each line, or all of them with
+/// `all`, makes in `lines`, accepted by default
+pub fn find_hunks(
+    path: &Path,
+    lines: &[String],
+    regex: &Regex,
+    replacement: &str,
+    all: bool,
+) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+
+    for (row, line) in lines.iter().enumerate() {
+        if !regex.is_match(line) {
+            continue;
+        }
+        let replaced = if all {
+            regex.replace_all(line, replacement)
+        } else {
+            regex.replacen(line, 1, replacement)
+        };
+
+        match hunks.last_mut() {
+            Some(hunk) if hunk.start + hunk.old.len() == row => {
+                hunk.old.push(line.clone());
+                hunk.new.extend(replaced.split('\n').map(str::to_string));
+            }
+            _ => hunks.push(Hunk {
+                path: path.to_path_buf(),
+                start: row,
+                old: vec![line.clone()],
+                new: replaced.split('\n').map(str::to_string).collect(),
+                accepted: true,
+            }),
+        }
+    }
+
+    hunks
+}
+
+/// The lines of a note with the accepted hunks of it applied
+pub fn apply(lines: &[String], hunks: &[&Hunk]) -> Vec<String> {
+    let mut applied = Vec::with_capacity(lines.len());
+    let mut row = 0;
+
+    for hunk in hunks.iter().filter(|hunk| hunk.accepted) {
+        applied.extend_from_slice(&lines[row..hunk.start]);
+        applied.extend_from_slice(&hunk.new);
+        row = hunk.start + hunk.old.len();
+    }
+    applied.extend_from_slice(&lines[row..]);
+
+    applied
+}
+
+/// A note as it was before a vault-wide replace, to put it back
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub disk: Vec<u8>,
+    /// The text of its buffer, when the note was open
+    pub buffer: Option<Vec<String>>,
+}
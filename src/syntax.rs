@@ -0,0 +1,388 @@
+use ratatui::style::{Color, Modifier, Style};
+
+/// What the code of a language looks like, enough to tell keywords, strings, numbers
+/// and comments apart a line at a time
+pub struct Language {
+    /// The info strings of the fences written in the language
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    /// Only ever highlighted up to the end of the line they start on
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const KEYWORD: Style = Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD);
+const STRING: Style = Style::new().fg(Color::Green);
+const NUMBER: Style = Style::new().fg(Color::Cyan);
+const COMMENT: Style = Style::new()
+    .fg(Color::DarkGray)
+    .add_modifier(Modifier::ITALIC);
+
+const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "include",
+    "int",
+    "long",
+    "namespace",
+    "new",
+    "nullptr",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "true",
+    "typedef",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+pub const LANGUAGES: [Language; 14] = [
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["bash", "sh", "shell", "zsh", "console"],
+        keywords: &[
+            "case", "do", "done", "echo", "elif", "else", "esac", "exit", "export", "fi", "for",
+            "function", "if", "in", "local", "return", "set", "then", "until", "while",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["json", "jsonc"],
+        keywords: &["false", "null", "true"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+    },
+    Language {
+        names: &["javascript", "js", "jsx", "typescript", "ts", "tsx"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "of",
+            "return",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "typeof",
+            "undefined",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "false",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+    },
+    Language {
+        names: &["c", "h", "cpp", "c++", "cc", "hpp"],
+        keywords: C_KEYWORDS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["java", "kotlin", "kt", "csharp", "cs", "c#"],
+        keywords: &[
+            "abstract",
+            "boolean",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "else",
+            "enum",
+            "extends",
+            "false",
+            "final",
+            "finally",
+            "for",
+            "fun",
+            "if",
+            "implements",
+            "import",
+            "int",
+            "interface",
+            "new",
+            "null",
+            "override",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "static",
+            "string",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "throws",
+            "true",
+            "try",
+            "val",
+            "var",
+            "void",
+            "while",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["yaml", "yml"],
+        keywords: &["false", "no", "null", "true", "yes"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["toml", "ini"],
+        keywords: &["false", "true"],
+        line_comments: &["#", ";"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["sql"],
+        keywords: &[
+            "and", "as", "by", "create", "delete", "desc", "distinct", "drop", "from", "group",
+            "having", "insert", "into", "join", "left", "limit", "not", "null", "on", "or",
+            "order", "select", "set", "table", "update", "values", "where",
+        ],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\'', '"'],
+    },
+    Language {
+        names: &["lua"],
+        keywords: &[
+            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
+            "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+        ],
+        line_comments: &["--"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["ruby", "rb"],
+        keywords: &[
+            "begin", "class", "def", "do", "else", "elsif", "end", "ensure", "false", "if",
+            "module", "nil", "require", "rescue", "return", "self", "true", "unless", "until",
+            "while", "yield",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["html", "xml", "svg"],
+        keywords: &[],
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        quotes: &['"', '\''],
+    },
+];
+
+/// The language of a fence's info string, like `rust` in ```` ```rust title="main.rs" ````
+pub fn language(info: &str) -> Option<usize> {
+    let name = info
+        .split_whitespace()
+        .next()?
+        .trim_start_matches('{')
+        .to_lowercase();
+    LANGUAGES
+        .iter()
+        .position(|language| language.names.contains(&name.as_str()))
+}
+
+/// Style the keywords, strings, numbers and comments of a line of code
+pub fn highlight(line: &str, language: &Language, styles: &mut [Style]) {
+    let chars: Vec<char> = line.chars().collect();
+    let starts_with = |col: usize, text: &str| {
+        text.chars()
+            .enumerate()
+            .all(|(offset, c)| chars.get(col + offset) == Some(&c))
+    };
+
+    let mut col = 0;
+    while col < chars.len() {
+        let c = chars[col];
+        let start = col;
+
+        if language
+            .line_comments
+            .iter()
+            .any(|comment| starts_with(col, comment))
+        {
+            styles[col..].fill(COMMENT);
+            return;
+        }
+        if let Some((open, close)) = language.block_comment
+            && starts_with(col, open)
+        {
+            col += open.chars().count();
+            while col < chars.len() && !starts_with(col, close) {
+                col += 1;
+            }
+            col = (col + close.chars().count()).min(chars.len());
+            styles[start..col].fill(COMMENT);
+            continue;
+        }
+
+        if language.quotes.contains(&c) {
+            col += 1;
+            while col < chars.len() && chars[col] != c {
+                col += if chars[col] == '\\' { 2 } else { 1 };
+            }
+            col = (col + 1).min(chars.len());
+            styles[start..col].fill(STRING);
+        } else if c.is_ascii_digit() {
+            while col < chars.len()
+                && (chars[col].is_alphanumeric() || matches!(chars[col], '.' | '_'))
+            {
+                col += 1;
+            }
+            styles[start..col].fill(NUMBER);
+        } else if c.is_alphabetic() || c == '_' {
+            while col < chars.len() && (chars[col].is_alphanumeric() || chars[col] == '_') {
+                col += 1;
+            }
+            let word: String = chars[start..col].iter().collect();
+            // SQL is written in either case
+            if language.keywords.contains(&word.as_str())
+                || (language.names[0] == "sql"
+                    && language.keywords.contains(&word.to_lowercase().as_str()))
+            {
+                styles[start..col].fill(KEYWORD);
+            }
+        } else {
+            col += 1;
+        }
+    }
+}
@@ -0,0 +1,796 @@
+use std::{cmp::Ordering, ops::Range, sync::LazyLock};
+
+use regex::Regex;
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthStr;
+
+use crate::view::jump;
+
+/// A cell of the row under the header, telling how its column is aligned
+static SEPARATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*:?-+:?\s*$").unwrap());
+
+/// The actions of `:table`, completed by Tab
+pub const ACTIONS: [&str; 12] = [
+    "align",
+    "row-above",
+    "row-below",
+    "delete-row",
+    "column-left",
+    "column-right",
+    "delete-column",
+    "sort",
+    "from-csv",
+    "from-tsv",
+    "to-csv",
+    "to-tsv",
+];
+
+/// A change to the table at the cursor, or the conversion of lines to or from a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableEdit {
+    Align,
+    InsertRow {
+        below: bool,
+    },
+    DeleteRow,
+    InsertColumn {
+        right: bool,
+    },
+    DeleteColumn,
+    /// Sort the rows under the header by a column counted from 1, or the cursor's
+    Sort {
+        column: Option<usize>,
+        descending: bool,
+    },
+    /// Turn lines of values separated by a comma or a tab into a table
+    FromDelimited(char),
+    ToDelimited(char),
+}
+
+impl TableEdit {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let Some((&action, rest)) = words.split_first() else {
+            return Err(format!("Argument required, one of {}", ACTIONS.join(", ")));
+        };
+
+        let edit = match action {
+            "align" => Self::Align,
+            "row-above" => Self::InsertRow { below: false },
+            "row-below" => Self::InsertRow { below: true },
+            "delete-row" => Self::DeleteRow,
+            "column-left" => Self::InsertColumn { right: false },
+            "column-right" => Self::InsertColumn { right: true },
+            "delete-column" => Self::DeleteColumn,
+            "sort" => {
+                let mut column = None;
+                let mut descending = false;
+                for word in rest {
+                    match *word {
+                        "desc" => descending = true,
+                        "asc" => descending = false,
+                        number => {
+                            column = Some(
+                                number
+                                    .parse()
+                                    .ok()
+                                    .filter(|&column| column > 0)
+                                    .ok_or_else(|| format!("Invalid column: {}", number))?,
+                            );
+                        }
+                    }
+                }
+                return Ok(Self::Sort { column, descending });
+            }
+            "from-csv" => Self::FromDelimited(','),
+            "from-tsv" => Self::FromDelimited('\t'),
+            "to-csv" => Self::ToDelimited(','),
+            "to-tsv" => Self::ToDelimited('\t'),
+            _ => return Err(format!("Unknown table action: {}", action)),
+        };
+        if !rest.is_empty() {
+            return Err(format!("Trailing characters: {}", rest.join(" ")));
+        }
+        Ok(edit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// The columns of the characters of each cell of a table row, between its pipes.
+/// A `\|` is a pipe inside a cell
+fn cell_spans(line: &str) -> Vec<Range<usize>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut pipes = Vec::new();
+    let mut escaped = false;
+    for (col, c) in chars.iter().enumerate() {
+        if *c == '|' && !escaped {
+            pipes.push(col);
+        }
+        escaped = *c == '\\' && !escaped;
+    }
+
+    let start = chars.iter().take_while(|c| c.is_whitespace()).count();
+    let end = chars.len() - chars.iter().rev().take_while(|c| c.is_whitespace()).count();
+    // The pipes at the ends of the row are optional
+    let first = match pipes.first() {
+        Some(&pipe) if pipe == start => {
+            pipes.remove(0);
+            pipe + 1
+        }
+        _ => start,
+    };
+    let last = match pipes.last() {
+        Some(&pipe) if pipe + 1 == end => {
+            pipes.pop();
+            pipe
+        }
+        _ => end.max(first),
+    };
+
+    let mut spans = Vec::new();
+    let mut cell_start = first;
+    for pipe in pipes {
+        spans.push(cell_start..pipe);
+        cell_start = pipe + 1;
+    }
+    spans.push(cell_start..last);
+    spans
+}
+
+/// The cells of a table row as written, `\|` and all
+pub fn cells(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    cell_spans(line)
+        .into_iter()
+        .map(|span| chars[span].iter().collect::<String>().trim().to_string())
+        .collect()
+}
+
+/// Whether the cells are those of the row under a table's header
+pub fn is_separator(cells: &[String]) -> bool {
+    cells.iter().all(|cell| SEPARATOR.is_match(cell))
+}
+
+/// The rows of the table a row is in: lines starting with `|`, the second of them being the
+/// row under the header
+pub fn find(lines: &[String], row: usize) -> Option<Range<usize>> {
+    let is_row = |line: &String| line.trim_start().starts_with('|');
+    if !is_row(lines.get(row)?) {
+        return None;
+    }
+
+    let mut start = row;
+    while start > 0 && is_row(&lines[start - 1]) {
+        start -= 1;
+    }
+    let mut end = row + 1;
+    while end < lines.len() && is_row(&lines[end]) {
+        end += 1;
+    }
+    (end - start >= 2 && is_separator(&cells(&lines[start + 1]))).then_some(start..end)
+}
+
+/// The cell a column of a row is in, and how far into the cell's text it is
+fn cell_at(line: &str, col: usize) -> (usize, usize) {
+    let spans = cell_spans(line);
+    let cell = spans
+        .iter()
+        .position(|span| col <= span.end)
+        .unwrap_or(spans.len() - 1);
+    (cell, col.saturating_sub(text_start(line, &spans[cell])))
+}
+
+/// The column of the first character of the text of a cell
+fn text_start(line: &str, span: &Range<usize>) -> usize {
+    let padding = line
+        .chars()
+        .skip(span.start)
+        .take(span.len())
+        .take_while(|c| *c == ' ')
+        .count();
+    // An empty cell is typed into after the space that follows its pipe
+    if padding == span.len() {
+        (span.start + 1).min(span.end)
+    } else {
+        span.start + padding
+    }
+}
+
+/// The column `offset` characters into the text of a cell, at most at its end
+fn cell_col(line: &str, cell: usize, offset: usize) -> usize {
+    let spans = cell_spans(line);
+    let span = &spans[cell.min(spans.len() - 1)];
+    let start = text_start(line, span);
+    let len = cells(line).get(cell).map_or(0, |text| text.chars().count());
+    start + offset.min(len)
+}
+
+#[derive(Debug, Clone)]
+struct Table {
+    indent: String,
+    /// The header first, without the row under it
+    rows: Vec<Vec<String>>,
+    alignments: Vec<Alignment>,
+}
+
+impl Table {
+    fn parse(lines: &[String]) -> Self {
+        let alignments = cells(&lines[1])
+            .iter()
+            .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::None,
+            })
+            .collect();
+        let rows = lines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != 1)
+            .map(|(_, line)| cells(line))
+            .collect();
+        let indent = lines[0].chars().take_while(|c| c.is_whitespace()).collect();
+
+        let mut table = Self {
+            indent,
+            rows,
+            alignments,
+        };
+        table.pad();
+        table
+    }
+
+    /// Give every row as many cells as the widest one
+    fn pad(&mut self) {
+        let columns = self
+            .rows
+            .iter()
+            .map(Vec::len)
+            .chain([self.alignments.len()])
+            .max()
+            .unwrap_or(1);
+        for row in &mut self.rows {
+            row.resize(columns, String::new());
+        }
+        self.alignments.resize(columns, Alignment::None);
+    }
+
+    fn columns(&self) -> usize {
+        self.alignments.len()
+    }
+
+    /// The rows of the table with its columns aligned
+    fn lines(&self) -> Vec<String> {
+        let widths: Vec<usize> = (0..self.columns())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .map(|row| row[column].width())
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+            })
+            .collect();
+
+        let line = |cells: Vec<String>| format!("{}| {} |", self.indent, cells.join(" | "));
+        let mut lines: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                line(
+                    row.iter()
+                        .zip(&widths)
+                        .zip(&self.alignments)
+                        .map(|((cell, &width), alignment)| {
+                            let pad = width - cell.width();
+                            let before = match alignment {
+                                Alignment::Right => pad,
+                                Alignment::Center => pad / 2,
+                                _ => 0,
+                            };
+                            format!("{}{}{}", " ".repeat(before), cell, " ".repeat(pad - before))
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let separator = widths
+            .iter()
+            .zip(&self.alignments)
+            .map(|(&width, alignment)| match alignment {
+                Alignment::None => "-".repeat(width),
+                Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+            })
+            .collect();
+        lines.insert(1, line(separator));
+        lines
+    }
+}
+
+/// The row of the table's rows a line of it holds, the line under the header counting as
+/// the first row under it. A table of only a header has no such row
+fn table_row(line: usize) -> usize {
+    line.saturating_sub(1).max(usize::from(line > 0))
+}
+
+/// The line of the table a row of it is on
+fn table_line(row: usize) -> usize {
+    if row == 0 { 0 } else { row + 1 }
+}
+
+/// Replace some lines of a textarea, leaving them be when nothing changes so that undo
+/// does not stop at them
+pub fn replace_lines(textarea: &mut TextArea, rows: Range<usize>, lines: &[String]) {
+    if textarea.lines()[rows.clone()] == *lines {
+        return;
+    }
+    textarea.cancel_selection();
+    jump(textarea, (rows.start, 0));
+    textarea.start_selection();
+    jump(textarea, (rows.end - 1, 0));
+    textarea.move_cursor(CursorMove::End);
+    textarea.insert_str(lines.join("\n"));
+}
+
+/// Align the table at the cursor, if any, keeping the cursor in its cell
+pub fn align(textarea: &mut TextArea) {
+    let (row, col) = textarea.cursor();
+    let Some(rows) = find(textarea.lines(), row) else {
+        return;
+    };
+    let (cell, offset) = cell_at(&textarea.lines()[row], col);
+    let lines = Table::parse(&textarea.lines()[rows.clone()]).lines();
+
+    let col = cell_col(&lines[row - rows.start], cell, offset);
+    replace_lines(textarea, rows, &lines);
+    jump(textarea, (row, col));
+}
+
+/// Align the table at the cursor and move to the start of the next or previous cell. Going
+/// past the last cell adds a row. Returns false when the cursor is not in a table
+pub fn next_cell(textarea: &mut TextArea, forward: bool) -> bool {
+    let (row, col) = textarea.cursor();
+    let Some(rows) = find(textarea.lines(), row) else {
+        return false;
+    };
+    let mut table = Table::parse(&textarea.lines()[rows.clone()]);
+    let (cell, _) = cell_at(&textarea.lines()[row], col);
+    let current = table_row(row - rows.start).min(table.rows.len() - 1);
+    let last = table.columns() - 1;
+
+    let (target, cell) = match (forward, cell) {
+        (true, cell) if cell < last => (current, cell + 1),
+        (true, _) => {
+            if current + 1 == table.rows.len() {
+                table.rows.push(vec![String::new(); table.columns()]);
+            }
+            (current + 1, 0)
+        }
+        (false, 0) if current == 0 => (0, 0),
+        (false, 0) => (current - 1, last),
+        (false, cell) => (current, cell.min(last + 1) - 1),
+    };
+
+    let lines = table.lines();
+    let line = table_line(target);
+    let col = cell_col(&lines[line], cell, 0);
+    replace_lines(textarea, rows.clone(), &lines);
+    jump(textarea, (rows.start + line, col));
+    true
+}
+
+/// The fields of a line of CSV or TSV, which may be quoted with `"` and hold `""`
+fn fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if fields.last().unwrap().trim().is_empty() => {
+                quoted = true;
+                fields.last_mut().unwrap().clear();
+            }
+            c if c == delimiter && !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+        .iter()
+        .map(|field| field.trim().replace('|', "\\|"))
+        .collect()
+}
+
+fn delimited(cells: &[String], delimiter: char) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            let cell = cell.replace("\\|", "|");
+            if cell.contains([delimiter, '"', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&delimiter.to_string())
+}
+
+/// Sort values like numbers when both are ones
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// Apply a table edit to the lines of a buffer. Conversions from CSV or TSV work on the
+/// given rows, or on the lines around the cursor up to blank ones.
+/// Returns where the cursor goes
+pub fn edit(
+    lines: &mut Vec<String>,
+    (row, col): (usize, usize),
+    range: Option<(usize, usize)>,
+    edit: &TableEdit,
+) -> Result<(usize, usize), String> {
+    if let TableEdit::FromDelimited(delimiter) = *edit {
+        let no_values = || Err("No values to make a table of".to_string());
+        let (first, last) = match range {
+            Some(range) => range,
+            None => {
+                let blank = |row: &usize| lines[*row].trim().is_empty();
+                if lines.get(row).is_none_or(|line| line.trim().is_empty()) {
+                    return no_values();
+                }
+                let first = (0..row).rev().find(blank).map_or(0, |blank| blank + 1);
+                // The cursor's line is not blank, so the first blank one is after it
+                let end = (row..lines.len()).find(blank).unwrap_or(lines.len());
+                (first, end - 1)
+            }
+        };
+        if lines[first..=last]
+            .iter()
+            .all(|line| line.trim().is_empty())
+        {
+            return no_values();
+        }
+        let mut rows: Vec<Vec<String>> = lines[first..=last]
+            .iter()
+            .map(|line| fields(line, delimiter))
+            .collect();
+        // A table has a header, so one line of values gets an empty one
+        if rows.len() == 1 {
+            rows.insert(0, vec![String::new(); rows[0].len()]);
+        }
+        let mut table = Table {
+            indent: String::new(),
+            rows,
+            alignments: Vec::new(),
+        };
+        table.pad();
+        lines.splice(first..=last, table.lines());
+        return Ok((first, 2));
+    }
+
+    let rows = find(lines, row).ok_or("Not in a table")?;
+    let mut table = Table::parse(&lines[rows.clone()]);
+    let (mut cell, offset) = cell_at(&lines[row], col);
+    cell = cell.min(table.columns() - 1);
+    let mut current = table_row(row - rows.start).min(table.rows.len() - 1);
+
+    match *edit {
+        TableEdit::ToDelimited(delimiter) => {
+            let converted: Vec<String> = table
+                .rows
+                .iter()
+                .map(|cells| delimited(cells, delimiter))
+                .collect();
+            lines.splice(rows.clone(), converted);
+            return Ok((rows.start, 0));
+        }
+        TableEdit::Align | TableEdit::FromDelimited(_) => (),
+        TableEdit::InsertRow { below } => {
+            if current == 0 && !below {
+                return Err("A table has no row above its header".to_string());
+            }
+            current += usize::from(below);
+            table
+                .rows
+                .insert(current, vec![String::new(); table.columns()]);
+        }
+        TableEdit::DeleteRow => {
+            if current == 0 {
+                return Err("The header of a table cannot be deleted".to_string());
+            }
+            table.rows.remove(current);
+            current = current.min(table.rows.len() - 1);
+        }
+        TableEdit::InsertColumn { right } => {
+            cell += usize::from(right);
+            for cells in &mut table.rows {
+                cells.insert(cell, String::new());
+            }
+            table.alignments.insert(cell, Alignment::None);
+        }
+        TableEdit::DeleteColumn => {
+            if table.columns() == 1 {
+                return Err("A table needs a column".to_string());
+            }
+            for cells in &mut table.rows {
+                cells.remove(cell);
+            }
+            table.alignments.remove(cell);
+            cell = cell.min(table.columns() - 1);
+        }
+        TableEdit::Sort { column, descending } => {
+            let column = column.map_or(cell, |column| column - 1);
+            if column >= table.columns() {
+                return Err(format!("The table has {} columns", table.columns()));
+            }
+            table.rows[1..].sort_by(|a, b| {
+                let ordering = compare(&a[column], &b[column]);
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+    }
+
+    let aligned = table.lines();
+    let line = table_line(current);
+    let col = cell_col(&aligned[line], cell, offset);
+    lines.splice(rows.clone(), aligned);
+    Ok((rows.start + line, col))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    /// A table of only a header, and the row under it
+    fn header() -> Vec<String> {
+        lines(&["| a | b |", "|---|---|"])
+    }
+
+    #[test]
+    fn header_only_tables() {
+        let mut table = header();
+        assert_eq!(
+            edit(&mut table, (1, 2), None, &TableEdit::Align),
+            Ok((0, 3))
+        );
+        assert_eq!(table, ["| a   | b   |", "| --- | --- |"]);
+
+        let mut table = header();
+        assert!(edit(&mut table, (1, 2), None, &TableEdit::DeleteRow).is_err());
+        assert_eq!(table, header());
+
+        let mut table = header();
+        let cursor = edit(
+            &mut table,
+            (1, 2),
+            None,
+            &TableEdit::InsertRow { below: true },
+        );
+        assert_eq!(cursor, Ok((2, 2)));
+        assert_eq!(table, ["| a   | b   |", "| --- | --- |", "|     |     |"]);
+
+        for right in [false, true] {
+            let mut table = header();
+            let edited = edit(&mut table, (1, 2), None, &TableEdit::InsertColumn { right });
+            assert!(edited.is_ok());
+            assert_eq!(table[0].matches('|').count(), 4);
+        }
+    }
+
+    #[test]
+    fn moving_from_the_row_under_a_header() {
+        let mut textarea = TextArea::new(header());
+        jump(&mut textarea, (1, 1));
+        assert!(next_cell(&mut textarea, true));
+        assert_eq!(textarea.cursor(), (0, 8));
+
+        let mut textarea = TextArea::new(header());
+        jump(&mut textarea, (1, 7));
+        assert!(next_cell(&mut textarea, true));
+        assert_eq!(textarea.lines().len(), 3);
+        assert_eq!(textarea.cursor(), (2, 2));
+    }
+
+    #[test]
+    fn nothing_to_make_a_table_of() {
+        let from_csv = TableEdit::FromDelimited(',');
+        let no_values = Err("No values to make a table of".to_string());
+        assert_eq!(edit(&mut lines(&[""]), (0, 0), None, &from_csv), no_values);
+        assert_eq!(
+            edit(&mut lines(&["a,b", ""]), (1, 0), None, &from_csv),
+            no_values
+        );
+        assert_eq!(edit(&mut Vec::new(), (0, 0), None, &from_csv), no_values);
+    }
+
+    /// A table with a header and two rows
+    fn two_rows() -> Vec<String> {
+        lines(&["| name | n |", "|:--|--:|", "| b | 10 |", "| a | 9 |"])
+    }
+
+    #[test]
+    fn actions() {
+        assert_eq!(TableEdit::parse("align"), Ok(TableEdit::Align));
+        assert_eq!(
+            TableEdit::parse("sort 2 desc"),
+            Ok(TableEdit::Sort {
+                column: Some(2),
+                descending: true
+            })
+        );
+        assert!(TableEdit::parse("sort 0").is_err());
+        assert!(TableEdit::parse("align now").is_err());
+        assert!(TableEdit::parse("").is_err());
+    }
+
+    #[test]
+    fn escaped_pipes() {
+        assert_eq!(cells(r"| a \| b | c |"), [r"a \| b", "c"]);
+        assert_eq!(cells("a | b"), ["a", "b"]);
+        assert!(is_separator(&cells("| :-- | :-: | --: |")));
+        assert!(!is_separator(&cells("| a | --- |")));
+    }
+
+    #[test]
+    fn aligning() {
+        let mut table = two_rows();
+        assert_eq!(
+            edit(&mut table, (2, 2), None, &TableEdit::Align),
+            Ok((2, 2))
+        );
+        assert_eq!(
+            table,
+            [
+                "| name |   n |",
+                "| :--- | --: |",
+                "| b    |  10 |",
+                "| a    |   9 |"
+            ]
+        );
+    }
+
+    #[test]
+    fn rows() {
+        let mut table = two_rows();
+        assert!(
+            edit(
+                &mut table,
+                (0, 2),
+                None,
+                &TableEdit::InsertRow { below: false }
+            )
+            .is_err()
+        );
+        assert!(edit(&mut table, (0, 2), None, &TableEdit::DeleteRow).is_err());
+
+        // The row under the header stands for the first row under it
+        let mut table = two_rows();
+        assert_eq!(
+            edit(&mut table, (1, 1), None, &TableEdit::DeleteRow),
+            Ok((2, 2))
+        );
+        assert_eq!(table[2], "| a    |   9 |");
+        assert_eq!(table.len(), 3);
+
+        let mut table = two_rows();
+        let cursor = edit(
+            &mut table,
+            (3, 2),
+            None,
+            &TableEdit::InsertRow { below: false },
+        );
+        assert_eq!(cursor, Ok((3, 2)));
+        assert_eq!(table[3], "|      |     |");
+    }
+
+    #[test]
+    fn columns() {
+        let mut table = two_rows();
+        let cursor = edit(
+            &mut table,
+            (2, 2),
+            None,
+            &TableEdit::InsertColumn { right: true },
+        );
+        assert_eq!(cursor, Ok((2, 9)));
+        assert_eq!(table[1], "| :--- | --- | --: |");
+
+        let mut table = two_rows();
+        assert_eq!(
+            edit(&mut table, (2, 6), None, &TableEdit::DeleteColumn),
+            Ok((2, 2))
+        );
+        assert_eq!(table, ["| name |", "| :--- |", "| b    |", "| a    |"]);
+        assert!(edit(&mut table, (2, 2), None, &TableEdit::DeleteColumn).is_err());
+    }
+
+    #[test]
+    fn sorting() {
+        let mut table = two_rows();
+        let by_number = TableEdit::Sort {
+            column: Some(2),
+            descending: false,
+        };
+        edit(&mut table, (0, 0), None, &by_number).unwrap();
+        assert_eq!(table[2], "| a    |   9 |");
+        let by_name = TableEdit::Sort {
+            column: None,
+            descending: true,
+        };
+        edit(&mut table, (2, 2), None, &by_name).unwrap();
+        assert_eq!(table[2], "| b    |  10 |");
+        let too_far = TableEdit::Sort {
+            column: Some(3),
+            descending: false,
+        };
+        assert!(edit(&mut table, (2, 2), None, &too_far).is_err());
+    }
+
+    #[test]
+    fn delimited_values() {
+        let mut csv = lines(&["intro", "", r#"name,"says ""hi"", a|b""#, "x,y", "", "end"]);
+        let from_csv = TableEdit::FromDelimited(',');
+        assert_eq!(edit(&mut csv, (3, 0), None, &from_csv), Ok((2, 2)));
+        assert_eq!(
+            csv[2..5],
+            [
+                r#"| name | says "hi", a\|b |"#,
+                "| ---- | --------------- |",
+                "| x    | y               |"
+            ]
+        );
+
+        let mut converted = csv[2..5].to_vec();
+        edit(&mut converted, (0, 0), None, &TableEdit::ToDelimited(',')).unwrap();
+        assert_eq!(converted, [r#"name,"says ""hi"", a|b""#, "x,y"]);
+
+        // A single line of values gets an empty header
+        let mut tsv = lines(&["a\tb"]);
+        edit(&mut tsv, (0, 0), None, &TableEdit::FromDelimited('\t')).unwrap();
+        assert_eq!(tsv, ["|     |     |", "| --- | --- |", "| a   | b   |"]);
+    }
+
+    #[test]
+    fn moving_between_cells() {
+        let mut textarea = TextArea::new(two_rows());
+        jump(&mut textarea, (3, 2));
+        assert!(next_cell(&mut textarea, false));
+        assert_eq!(textarea.cursor(), (2, 10));
+        assert!(next_cell(&mut textarea, true));
+        assert_eq!(textarea.cursor(), (3, 2));
+
+        jump(&mut textarea, (0, 2));
+        assert!(next_cell(&mut textarea, false));
+        assert_eq!(textarea.cursor(), (0, 2));
+
+        let mut textarea = TextArea::new(lines(&["not a table"]));
+        assert!(!next_cell(&mut textarea, true));
+    }
+}
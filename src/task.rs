@@ -0,0 +1,404 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+
+use crate::{date::parse_natural, note};
+
+/// A list item with a checkbox: `- [ ] text`, `* [x] text` or `1. [ ] text`
+static TASK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*(?:[-*+]|\d+[.)])\s+\[)(.)\](.*)$").unwrap());
+
+/// A due date written as `due:2026-10-20` or Dataview's `[due:: 2026-10-20]`
+static DUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bdue::?\s*(\d{4}-\d{2}-\d{2})").unwrap());
+
+/// A date of the Obsidian Tasks plugin: due, scheduled, start or done
+static DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(📅|⏳|🛫|✅)\u{FE0F}?\s*(\d{4}-\d{2}-\d{2})").unwrap());
+
+/// The done date of the Tasks plugin with the space before it
+static DONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*✅\u{FE0F}?\s*\d{4}-\d{2}-\d{2}").unwrap());
+
+/// A recurrence like `🔁 every week`, up to the next emoji, tag or Dataview field
+static RECURRENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"🔁\u{FE0F}?\s*([^📅⏳🛫✅➕🔺⏫🔼🔽⏬#\[]*)").unwrap());
+
+/// The priorities of the Tasks plugin from highest to lowest, a task without one
+/// coming between medium and low
+const PRIORITIES: [&str; 5] = ["🔺", "⏫", "🔼", "🔽", "⏬"];
+
+/// A task found in a note
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub path: PathBuf,
+    pub row: usize,
+    pub done: bool,
+    /// What follows the checkbox
+    pub text: String,
+    pub due: Option<NaiveDate>,
+    pub scheduled: Option<NaiveDate>,
+    pub start: Option<NaiveDate>,
+    /// From 0 for the highest to 5 for the lowest, 3 when none is given
+    pub priority: usize,
+    /// The rule after `🔁`, like `every week` or `every 2 days when done`
+    pub recurrence: Option<String>,
+}
+
+impl Task {
+    pub fn parse(path: &Path, row: usize, line: &str) -> Option<Self> {
+        let captures = TASK.captures(line)?;
+        let text = captures[3].trim().to_string();
+
+        let date = |emoji: &str| {
+            DATE.captures_iter(&text)
+                .find(|captures| &captures[1] == emoji)
+                .and_then(|captures| parse_day(&captures[2]))
+        };
+        let due = date("📅").or_else(|| {
+            DUE.captures(&text)
+                .and_then(|captures| parse_day(&captures[1]))
+        });
+        let priority = match PRIORITIES.iter().position(|emoji| text.contains(emoji)) {
+            Some(index) if index >= 3 => index + 1,
+            Some(index) => index,
+            None => 3,
+        };
+        let recurrence = RECURRENCE
+            .captures(&text)
+            .map(|captures| {
+                let rule = &captures[1];
+                rule[..rule.find("due:").unwrap_or(rule.len())]
+                    .trim()
+                    .to_string()
+            })
+            .filter(|rule| !rule.is_empty());
+
+        Some(Self {
+            path: path.to_path_buf(),
+            row,
+            done: &captures[2] != " ",
+            due,
+            scheduled: date("⏳"),
+            start: date("🛫"),
+            priority,
+            recurrence,
+            text,
+        })
+    }
+
+    /// The day a task is planned for: when it is due, or else when it is scheduled
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.due.or(self.scheduled)
+    }
+}
+
+fn parse_day(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+}
+
+/// The tasks of a note, in order
+pub fn tasks(path: &Path, lines: &[String]) -> Vec<Task> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| Task::parse(path, row, line))
+        .collect()
+}
+
+/// Check an open task, marking it done `today`, or uncheck a done one. A recurring task
+/// that is checked gets its next occurrence on the line below it.
+/// Returns how many lines there now are where the task was, 2 with a next occurrence, and
+/// why there is none when the rule of the task could not be read. The task is checked
+/// all the same
+pub fn toggle(
+    lines: &mut Vec<String>,
+    row: usize,
+    today: NaiveDate,
+) -> Result<(usize, Option<String>), String> {
+    let line = lines.get(row).ok_or("No such line")?;
+    let captures = TASK.captures(line).ok_or("Not a task")?;
+    let (head, text) = (&captures[1], without_completion(&captures[3]));
+
+    if &captures[2] != " " {
+        lines[row] = format!("{} ]{}", head, text);
+        return Ok((1, None));
+    }
+
+    let task = Task::parse(Path::new(""), row, line).unwrap();
+    let next = task
+        .recurrence
+        .as_ref()
+        .map(|rule| next_occurrence(&text, &task, rule, today))
+        .map(|next| next.map(|next| format!("{} ]{}", head, next)));
+
+    lines[row] = format!("{}x]{} ✅ {}", head, text, today.format("%Y-%m-%d"));
+    match next {
+        Some(Ok(next)) => {
+            lines.insert(row + 1, next);
+            Ok((2, None))
+        }
+        Some(Err(error)) => Ok((1, Some(error + ", no next occurrence was added"))),
+        None => Ok((1, None)),
+    }
+}
+
+/// The text of a task without its done date and the space before it
+fn without_completion(text: &str) -> String {
+    DONE.replace_all(text, "").trim_end().to_string()
+}
+
+/// The text of the next occurrence of a recurring task: its dates moved by as much as the
+/// rule moves the due date, or the scheduled or start date when it has none.
+/// With `when done` the rule counts from `today` instead
+fn next_occurrence(
+    text: &str,
+    task: &Task,
+    rule: &str,
+    today: NaiveDate,
+) -> Result<String, String> {
+    let invalid = || format!("Invalid recurrence: {}", rule);
+    let (rule, when_done) = match rule.strip_suffix("when done") {
+        Some(rule) => (rule.trim(), true),
+        None => (rule, false),
+    };
+
+    let Some(reference) = task.due.or(task.scheduled).or(task.start) else {
+        next_date(rule, today).ok_or_else(invalid)?;
+        return Ok(text.to_string());
+    };
+    let from = if when_done { today } else { reference };
+    let delta = next_date(rule, from).ok_or_else(invalid)? - reference;
+    let moved = |date: NaiveDate| (date + delta).format("%Y-%m-%d").to_string();
+
+    let text = DATE.replace_all(text, |captures: &regex::Captures| {
+        parse_day(&captures[2]).map_or(captures[0].to_string(), |date| {
+            format!("{} {}", &captures[1], moved(date))
+        })
+    });
+    Ok(DUE
+        .replace_all(&text, |captures: &regex::Captures| {
+            parse_day(&captures[1]).map_or(captures[0].to_string(), |date| {
+                captures[0].replace(&captures[1], &moved(date))
+            })
+        })
+        .to_string())
+}
+
+/// The day after `date` that a rule like `every week`, `every 2 months`, `every friday`
+/// or `every weekday` falls on
+fn next_date(rule: &str, date: NaiveDate) -> Option<NaiveDate> {
+    let rule = rule.to_lowercase();
+    match rule.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["every", "weekday"] => {
+            let mut next = date.succ_opt()?;
+            while next.weekday().number_from_monday() > 5 {
+                next = next.succ_opt()?;
+            }
+            Some(next)
+        }
+        ["every", unit] => parse_natural(&format!("next {}", unit), date),
+        ["every", count, unit] => parse_natural(&format!("in {} {}", count, unit), date),
+        _ => None,
+    }
+}
+
+/// Which tasks the task view shows, typed as words: `#tag`, `due:DATE`, `due<DATE`,
+/// `due>DATE` or `due` for any due date, and any other word to look for in the text.
+/// DATE is a date like 2026-10-20, today or friday
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    tags: Vec<String>,
+    due: Vec<(char, NaiveDate)>,
+    has_due: bool,
+    words: Vec<String>,
+}
+
+impl Filter {
+    pub fn parse(query: &str, today: NaiveDate) -> Result<Self, String> {
+        let mut filter = Self::default();
+
+        for word in query.split_whitespace() {
+            if word.starts_with('#') {
+                filter.tags.push(word.to_lowercase());
+            } else if word == "due" {
+                filter.has_due = true;
+            } else if let Some(rest) = word.strip_prefix("due")
+                && let Some(comparison @ (':' | '<' | '>')) = rest.chars().next()
+            {
+                let date = parse_natural(&rest[1..], today)
+                    .ok_or_else(|| format!("Not a date: {}", &rest[1..]))?;
+                filter.due.push((comparison, date));
+            } else {
+                filter.words.push(word.to_lowercase());
+            }
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        let text = task.text.to_lowercase();
+        let tags: Vec<String> = note::tags(&text).map(str::to_string).collect();
+
+        // Like in Obsidian, #work takes in #work/project too
+        self.tags.iter().all(|tag| {
+            tags.iter()
+                .any(|other| other == tag || other.starts_with(&format!("{}/", tag)))
+        }) && (!self.has_due || task.due.is_some())
+            && self.due.iter().all(|&(comparison, date)| match task.due {
+                Some(due) if comparison == '<' => due < date,
+                Some(due) if comparison == '>' => due > date,
+                Some(due) => due == date,
+                None => false,
+            })
+            && self.words.iter().all(|word| text.contains(word.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sunday 18 October 2026
+    const TODAY: &str = "2026-10-18";
+
+    /// The lines a task becomes when it is checked or unchecked on Sunday 18 October 2026
+    fn toggled(line: &str) -> Vec<String> {
+        let mut lines = vec![line.to_string()];
+        let (count, warning) = toggle(&mut lines, 0, parse_day(TODAY).unwrap()).unwrap();
+        assert_eq!(count, lines.len());
+        assert_eq!(warning, None);
+        lines
+    }
+
+    #[test]
+    fn days_and_weeks() {
+        assert_eq!(
+            toggled("- [ ] Water plants 🔁 every day 📅 2026-10-16"),
+            [
+                "- [x] Water plants 🔁 every day 📅 2026-10-16 ✅ 2026-10-18",
+                "- [ ] Water plants 🔁 every day 📅 2026-10-17",
+            ]
+        );
+        assert_eq!(
+            toggled("- [ ] Review 🔁 every week 📅 2026-10-16")[1],
+            "- [ ] Review 🔁 every week 📅 2026-10-23"
+        );
+        assert_eq!(
+            toggled("* [ ] Review 🔁 every 2 weeks 📅 2026-10-16")[1],
+            "* [ ] Review 🔁 every 2 weeks 📅 2026-10-30"
+        );
+        assert_eq!(
+            toggled("  1. [ ] Back up 🔁 every 3 days ⏳ 2026-10-16")[1],
+            "  1. [ ] Back up 🔁 every 3 days ⏳ 2026-10-19"
+        );
+    }
+
+    #[test]
+    fn months_and_years() {
+        assert_eq!(
+            toggled("- [ ] Invoice 🔁 every month 📅 2026-01-31")[1],
+            "- [ ] Invoice 🔁 every month 📅 2026-02-28"
+        );
+        assert_eq!(
+            toggled("- [ ] Taxes 🔁 every year 📅 2026-04-15")[1],
+            "- [ ] Taxes 🔁 every year 📅 2027-04-15"
+        );
+    }
+
+    #[test]
+    fn weekdays() {
+        // From a Friday the next weekday is the Monday after
+        assert_eq!(
+            toggled("- [ ] Standup 🔁 every weekday 📅 2026-10-16")[1],
+            "- [ ] Standup 🔁 every weekday 📅 2026-10-19"
+        );
+        assert_eq!(
+            toggled("- [ ] Standup 🔁 every weekday 📅 2026-10-14")[1],
+            "- [ ] Standup 🔁 every weekday 📅 2026-10-15"
+        );
+        assert_eq!(
+            toggled("- [ ] Groceries 🔁 every Friday 📅 2026-10-16")[1],
+            "- [ ] Groceries 🔁 every Friday 📅 2026-10-23"
+        );
+        assert_eq!(
+            toggled("- [ ] Groceries 🔁 every mon 📅 2026-10-16")[1],
+            "- [ ] Groceries 🔁 every mon 📅 2026-10-19"
+        );
+    }
+
+    #[test]
+    fn when_done() {
+        // Counted from today, every date moving as much as the due date
+        assert_eq!(
+            toggled("- [ ] Haircut 🔁 every week when done ⏳ 2026-09-30 📅 2026-10-01")[1],
+            "- [ ] Haircut 🔁 every week when done ⏳ 2026-10-24 📅 2026-10-25"
+        );
+        assert_eq!(
+            toggled("- [ ] Haircut 🔁 every week ⏳ 2026-09-30 📅 2026-10-01")[1],
+            "- [ ] Haircut 🔁 every week ⏳ 2026-10-07 📅 2026-10-08"
+        );
+        // With no date at all the next occurrence is the same task
+        assert_eq!(
+            toggled("- [ ] Stretch 🔁 every day when done")[1],
+            "- [ ] Stretch 🔁 every day when done"
+        );
+    }
+
+    #[test]
+    fn due_fields() {
+        assert_eq!(
+            toggled("- [ ] Rent 🔁 every month [due:: 2026-10-01]")[1],
+            "- [ ] Rent 🔁 every month [due:: 2026-11-01]"
+        );
+        assert_eq!(
+            toggled("- [ ] Rent 🔁 every month due:2026-10-01 #home")[1],
+            "- [ ] Rent 🔁 every month due:2026-11-01 #home"
+        );
+        assert_eq!(
+            toggled("- [ ] Rent 🔁 every 2 days when done [due::2026-10-01]")[1],
+            "- [ ] Rent 🔁 every 2 days when done [due::2026-10-20]"
+        );
+    }
+
+    #[test]
+    fn unchecking() {
+        assert_eq!(
+            toggled("- [x] Water plants 📅 2026-10-16 ✅ 2026-10-17"),
+            ["- [ ] Water plants 📅 2026-10-16"]
+        );
+        assert_eq!(
+            toggled("- [X] Call back ✅ 2026-10-17 #work"),
+            ["- [ ] Call back #work"]
+        );
+        // A recurring task does not recur when it is unchecked
+        assert_eq!(
+            toggled("- [x] Review 🔁 every week 📅 2026-10-16 ✅ 2026-10-18"),
+            ["- [ ] Review 🔁 every week 📅 2026-10-16"]
+        );
+        assert_eq!(
+            toggled("- [ ] Call back"),
+            ["- [x] Call back ✅ 2026-10-18"]
+        );
+    }
+
+    #[test]
+    fn unreadable_rules() {
+        let mut lines = vec!["- [ ] Report 🔁 every month on the 1st 📅 2026-10-01".to_string()];
+        let (count, warning) = toggle(&mut lines, 0, parse_day(TODAY).unwrap()).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            lines,
+            ["- [x] Report 🔁 every month on the 1st 📅 2026-10-01 ✅ 2026-10-18"]
+        );
+        assert!(warning.unwrap().contains("every month on the 1st"));
+
+        assert!(toggle(&mut vec!["Just a line".to_string()], 0, NaiveDate::MIN).is_err());
+        assert!(toggle(&mut Vec::new(), 0, NaiveDate::MIN).is_err());
+    }
+}
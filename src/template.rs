@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, Months, TimeDelta};
+
+use crate::{date::format_date, error::VaultError};
+
+/// A template split into text and the `{{variables}}` to fill in
+#[derive(Debug, Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Variable(Variable),
+}
+
+#[derive(Debug, Clone)]
+enum Variable {
+    /// The name of the note, without its extension
+    Title,
+    /// `{{date}}`, `{{time}}`, `{{date+1d:YYYY-MM-DD}}`: now moved by the offsets, in a Moment format
+    Date {
+        offsets: Vec<Offset>,
+        format: String,
+    },
+    /// Where the cursor goes once the template is inserted
+    Cursor,
+    /// Asked for when the template is inserted
+    Prompt(String),
+    /// A field of the frontmatter of the note or of the template
+    Field(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Offset {
+    amount: i64,
+    unit: char,
+}
+
+/// What a template is filled in with
+pub struct Context {
+    pub title: String,
+    pub now: DateTime<Local>,
+    /// The answers to the prompts, by their label
+    pub answers: HashMap<String, String>,
+    /// Frontmatter fields, the note's taking precedence over the template's
+    pub fields: HashMap<String, Vec<String>>,
+}
+
+impl Context {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            now: Local::now(),
+            answers: HashMap::new(),
+            fields: HashMap::new(),
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, VaultError> {
+        let mut pieces = Vec::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{{") {
+            let line = text[..text.len() - rest.len() + start]
+                .matches('\n')
+                .count()
+                + 1;
+            let error = |message: String| {
+                VaultError::Command(format!("Template error on line {}: {}", line, message))
+            };
+
+            if start > 0 {
+                pieces.push(Piece::Text(rest[..start].to_string()));
+            }
+            let inner = &rest[start + 2..];
+            let end = inner
+                .find("}}")
+                .filter(|end| !inner[..*end].contains('\n'))
+                .ok_or_else(|| error("{{ is never closed".to_string()))?;
+
+            pieces.push(Piece::Variable(
+                parse_variable(inner[..end].trim()).map_err(error)?,
+            ));
+            rest = &inner[end + 2..];
+        }
+        if !rest.is_empty() {
+            pieces.push(Piece::Text(rest.to_string()));
+        }
+
+        Ok(Self { pieces })
+    }
+
+    /// The labels of the prompts, each once, in order
+    pub fn prompts(&self) -> Vec<String> {
+        let mut labels: Vec<String> = Vec::new();
+        for piece in &self.pieces {
+            if let Piece::Variable(Variable::Prompt(label)) = piece
+                && !labels.contains(label)
+            {
+                labels.push(label.clone());
+            }
+        }
+        labels
+    }
+
+    /// The filled in text, and the row and column of `{{cursor}}` in it
+    pub fn expand(
+        &self,
+        context: &Context,
+    ) -> Result<(String, Option<(usize, usize)>), VaultError> {
+        let mut text = String::new();
+        let mut cursor = None;
+
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(part) => text += part,
+                Piece::Variable(Variable::Title) => text += &context.title,
+                Piece::Variable(Variable::Date { offsets, format }) => {
+                    let date = offsets
+                        .iter()
+                        .try_fold(context.now, |date, offset| offset.apply(date))
+                        .ok_or_else(|| {
+                            VaultError::Command("Template error: date out of range".to_string())
+                        })?;
+                    text += &format_date(&date, format);
+                }
+                Piece::Variable(Variable::Cursor) => {
+                    let row = text.matches('\n').count();
+                    let line_start = text.rfind('\n').map_or(0, |newline| newline + 1);
+                    cursor.get_or_insert((row, text[line_start..].chars().count()));
+                }
+                Piece::Variable(Variable::Prompt(label)) => {
+                    text += context.answers.get(label).map_or("", String::as_str);
+                }
+                Piece::Variable(Variable::Field(name)) => {
+                    let values = context.fields.get(name).ok_or_else(|| {
+                        VaultError::Command(format!(
+                            "Template error: {{{{{}}}}} is not a template variable or frontmatter field",
+                            name
+                        ))
+                    })?;
+                    text += &values.join(", ");
+                }
+            }
+        }
+
+        Ok((text, cursor))
+    }
+}
+
+fn parse_variable(inner: &str) -> Result<Variable, String> {
+    match inner {
+        "" => return Err("empty {{}}".to_string()),
+        "title" => return Ok(Variable::Title),
+        "cursor" => return Ok(Variable::Cursor),
+        _ => (),
+    }
+
+    if let Some(label) = inner.strip_prefix("prompt:") {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err("{{prompt:}} needs a label".to_string());
+        }
+        return Ok(Variable::Prompt(label.to_string()));
+    }
+
+    for (name, default_format) in [("date", "YYYY-MM-DD"), ("time", "HH:mm")] {
+        let Some(rest) = inner.strip_prefix(name) else {
+            continue;
+        };
+        if !matches!(rest.chars().next(), None | Some(':' | '+' | '-')) {
+            continue;
+        }
+
+        let (offsets, format) = match rest.split_once(':') {
+            Some((offsets, format)) => (offsets, format),
+            None => (rest, default_format),
+        };
+        return Ok(Variable::Date {
+            offsets: parse_offsets(offsets.trim())?,
+            format: format.to_string(),
+        });
+    }
+
+    if inner
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '))
+    {
+        return Ok(Variable::Field(inner.to_string()));
+    }
+
+    Err(format!("{{{{{}}}}} is not a valid variable", inner))
+}
+
+/// Offsets like `+1d` or `-2w+3h`, in years, Months, weeks, days, hours or minutes
+fn parse_offsets(mut offsets: &str) -> Result<Vec<Offset>, String> {
+    let mut parsed = Vec::new();
+
+    while let Some(sign) = offsets.chars().next() {
+        let number = offsets[sign.len_utf8()..].trim_start();
+        let digits = number
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(number.len());
+        let unit = number[digits..].chars().next();
+
+        let (Some(unit @ ('y' | 'M' | 'w' | 'd' | 'h' | 'm')), true) =
+            (unit, sign == '+' || sign == '-')
+        else {
+            return Err(format!(
+                "invalid date offset {}, use something like +1d, -2w or +3M",
+                offsets
+            ));
+        };
+        let amount: i64 = if digits == 0 {
+            1
+        } else {
+            number[..digits]
+                .parse()
+                .map_err(|_| format!("invalid date offset {}", offsets))?
+        };
+
+        parsed.push(Offset {
+            amount: if sign == '-' { -amount } else { amount },
+            unit,
+        });
+        offsets = number[digits + 1..].trim_start();
+    }
+
+    Ok(parsed)
+}
+
+impl Offset {
+    fn apply(&self, date: DateTime<Local>) -> Option<DateTime<Local>> {
+        let months = |months: i64| Months::new(months.unsigned_abs() as u32);
+        match self.unit {
+            'y' | 'M' => {
+                let count = if self.unit == 'y' {
+                    self.amount * 12
+                } else {
+                    self.amount
+                };
+                if count < 0 {
+                    date.checked_sub_months(months(count))
+                } else {
+                    date.checked_add_months(months(count))
+                }
+            }
+            'w' => date.checked_add_signed(TimeDelta::try_weeks(self.amount)?),
+            'd' => date.checked_add_signed(TimeDelta::try_days(self.amount)?),
+            'h' => date.checked_add_signed(TimeDelta::try_hours(self.amount)?),
+            _ => date.checked_add_signed(TimeDelta::try_minutes(self.amount)?),
+        }
+    }
+}
@@ -1,26 +1,41 @@
 use std::{
     fs::{self, File},
     io::{self},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
-use chrono::Local;
-use crossterm::event::{Event, read};
+use chrono::{Local, NaiveDate};
+use crossterm::event::{Event, KeyCode, KeyEvent, read};
 use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use ratatui::{
     DefaultTerminal,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Clear, Paragraph},
+    widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState},
 };
-use tui_textarea::{Input, Key, TextArea};
+use regex::{Regex, RegexBuilder};
+use tui_textarea::{CursorMove, Input, Key, TextArea};
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    command::Command,
-    editor::Editor,
+    command::{
+        Argument, COMMANDS, Command, Completion, ExCommand, Range, Substitute, completion_target,
+    },
+    complete::{self, Candidate, Kind},
+    config::{Config, DATA_DIR, OPTIONS},
+    date::{PHRASE_WORDS, format_date, parse_natural},
+    editor::{Editor, contents, write_file, write_lines},
     error::VaultError,
-    vim::{Mode, Search, Transition, Vim},
+    history::{Browsing, History},
+    markdown::CALLOUTS,
+    note, preview,
+    replace::{self, Backup, Hunk},
+    table,
+    task::{self, Filter, Task},
+    template::{Context, Template},
+    view::jump,
+    vim::{Mode, Search, Transition, Vim, first_non_blank},
 };
 
 #[derive(Debug)]
@@ -30,6 +45,11 @@ pub struct Vault<'a> {
     current_tab: usize,
     vim: Vim,
     file_paths: Vec<PathBuf>,
+    config: Config,
+    command_history: History,
+    search_history: History,
+    /// What the last `:replace` changed, for `:undoreplace`
+    last_replace: Vec<Backup>,
     run: bool,
 }
 
@@ -43,11 +63,18 @@ impl Vault<'_> {
             current_tab: 0,
             vim: Vim::new(Mode::Normal),
             file_paths,
+            config: Config::default(),
+            command_history: History::load("command_history"),
+            search_history: History::load("search_history"),
+            last_replace: Vec::new(),
             run: true,
         }
     }
 
     pub fn run(&mut self) {
+        let result = self.config.load();
+        self.handle_error(result);
+
         // When provided with a file instead of a dir
         // Open the file then update self.file_paths and the homepage with pwd
         if self.file_paths.len() == 1 {
@@ -78,19 +105,39 @@ impl Vault<'_> {
 
                 Line::from(status_bar)
             };
+            let search_count = self.tabs[self.current_tab]
+                .search_count()
+                .map(|(current, total)| format!("[{}/{}]", current, total))
+                .unwrap_or_default();
 
             // TODO: handle this error
             self.terminal
                 .draw(|frame| {
                     let chunks = layout.split(frame.area());
 
-                    frame.render_widget(self.tabs[self.current_tab].textarea(), chunks[0]);
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[0],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
                     frame.render_widget(Paragraph::new(status_bar), chunks[1]);
+                    frame.render_widget(
+                        Paragraph::new(search_count).alignment(Alignment::Right),
+                        chunks[1],
+                    );
                 })
                 .unwrap();
 
+            let (tab, cursor) = (
+                self.current_tab,
+                self.tabs[self.current_tab].textarea().cursor(),
+            );
             let result = self.input();
             self.handle_error(result);
+            if self.current_tab == tab {
+                self.tabs[tab].leave_folds(cursor);
+            }
         }
     }
 
@@ -102,6 +149,21 @@ impl Vault<'_> {
         Ok(input.unwrap())
     }
 
+    fn read_input() -> Result<Input, VaultError> {
+        match Self::read()? {
+            // tui-textarea has no key for Shift-Tab
+            Event::Key(KeyEvent {
+                code: KeyCode::BackTab,
+                ..
+            }) => Ok(Input {
+                key: Key::Tab,
+                shift: true,
+                ..Input::default()
+            }),
+            event => Ok(event.into()),
+        }
+    }
+
     fn handle_error<T>(&mut self, result: Result<T, VaultError>) {
         if result.is_err() {
             match result.err().unwrap() {
@@ -113,26 +175,50 @@ impl Vault<'_> {
                     let result = self.render_notification_area("Failed to read input".to_string());
                     self.handle_error(result);
                 }
+                VaultError::Command(message) => {
+                    let result = self.render_notification_area(message);
+                    self.handle_error(result);
+                }
             }
         }
     }
 
     fn input(&mut self) -> Result<(), VaultError> {
+        let input = Self::read_input()?;
         let tab = &mut self.tabs[self.current_tab];
-        self.vim = match self
+        let transition = self
             .vim
-            .exec(Self::read()?.into(), &mut tab.textareas[tab.current])
-        {
-            Transition::Mode(mode) if self.vim.mode != mode => Vim::new(mode),
+            .exec(input, &mut tab.textareas[tab.current], &self.config);
+        self.transition(transition)
+    }
+
+    /// Act on what Vim made of a key
+    fn transition(&mut self, transition: Transition) -> Result<(), VaultError> {
+        self.vim = match transition {
+            Transition::Mode(mode) if self.vim.mode != mode => self.vim.with_mode(mode),
             Transition::Nop | Transition::Mode(_) => self.vim.clone(),
             Transition::Pending(input) => self.vim.with_pending(input),
-            Transition::CommandMode => {
-                let vim = self.render_command_area()?;
-                vim
-            }
+            Transition::CommandMode => self.render_command_area("")?,
             Transition::CommandExec(command) => {
+                let focused = (self.current_tab, self.tabs[self.current_tab].current);
                 self.exec_command(command)?;
-                self.vim.clone()
+                // A selection belongs to its buffer, so visual mode ends when another is
+                // focused
+                if self.vim.mode.is_visual()
+                    && focused != (self.current_tab, self.tabs[self.current_tab].current)
+                {
+                    let (tab, buffer) = focused;
+                    if let Some(textarea) = self
+                        .tabs
+                        .get_mut(tab)
+                        .and_then(|tab| tab.textareas.get_mut(buffer))
+                    {
+                        textarea.cancel_selection();
+                    }
+                    self.vim.with_mode(Mode::Normal)
+                } else {
+                    self.vim.clone()
+                }
             }
             Transition::Search(search) => match search {
                 Search::Open => {
@@ -155,44 +241,26 @@ impl Vault<'_> {
                     self.render_search_area(previous_search)?;
                     return Ok(());
                 }
-                Search::Forward => {
+                Search::Forward | Search::Backward => {
                     let tab = &mut self.tabs[self.current_tab];
-                    tab.textareas[tab.current].search_forward(false);
-                    return Ok(());
-                }
-                Search::Backward => {
-                    let tab = &mut self.tabs[self.current_tab];
-                    tab.textareas[tab.current].search_back(false);
+                    let textarea = &mut tab.textareas[tab.current];
+                    let found = match search {
+                        Search::Forward => textarea.search_forward(false),
+                        _ => textarea.search_back(false),
+                    };
+                    if let Some(pattern) = textarea.search_pattern()
+                        && !found
+                    {
+                        return Err(VaultError::Command(format!(
+                            "Pattern not found: {}",
+                            pattern.as_str()
+                        )));
+                    }
                     return Ok(());
                 }
             },
             Transition::AutoComplete => {
-                let mut link = "".to_string();
-                let (row, _) = self.tabs[self.current_tab].textarea().cursor();
-                let lines = self.tabs[self.current_tab].textarea().lines();
-
-                if !lines[row].contains("[[") && !lines[row].contains("]]") {
-                    return Ok(());
-                }
-
-                if lines[row].contains("[[") && !lines[row].contains("]]") {
-                    let inner_link = self.render_autocomplete()?;
-                    if inner_link == "" {
-                        return Ok(());
-                    }
-                    // Remove the .md file extension
-                    let inner_link = &inner_link[0..inner_link.len() - 3];
-                    let inner_link = inner_link.to_string() + "]]";
-                    link = inner_link;
-                }
-                let (row, _) = self.tabs[self.current_tab].textarea().cursor();
-                let lines = self.tabs[self.current_tab].textarea().lines();
-                let start = lines[row].to_string().find("[[").unwrap() + 2;
-                let current = &mut self.tabs[self.current_tab];
-                current.textareas[current.current]
-                    .move_cursor(tui_textarea::CursorMove::Jump(row as u16, start as u16));
-                current.textareas[current.current].delete_line_by_end();
-                current.textareas[current.current].insert_str(link);
+                self.render_completion()?;
                 return Ok(());
             }
         };
@@ -202,7 +270,7 @@ impl Vault<'_> {
     fn open_file(&mut self, path: PathBuf) -> Result<(), VaultError> {
         for i in 0..self.tabs[self.current_tab].textareas.len() {
             let tab = &mut self.tabs[self.current_tab];
-            if &tab.paths[i] == &path {
+            if tab.paths[i] == path {
                 tab.current = i;
                 return Ok(());
             }
@@ -213,16 +281,19 @@ impl Vault<'_> {
         Ok(())
     }
 
-    fn open_template(path: PathBuf) -> Result<Editor<'static>, VaultError> {
-        let mut editor = Editor::new();
-        editor.open(path)?;
-        Ok(editor)
-    }
-
-    fn render_command_area(&mut self) -> Result<Vim, VaultError> {
+    fn render_command_area(&mut self, initial: &str) -> Result<Vim, VaultError> {
         let mut command_area = TextArea::default();
         command_area.set_cursor_line_style(Style::default());
-        command_area.set_block(Block::bordered().title("Command"));
+
+        // Like Vim, a command typed from visual mode works on the selected lines
+        if self.vim.mode.is_visual() {
+            command_area.insert_str("'<,'>");
+        }
+        command_area.insert_str(initial);
+
+        // The start of the completed word, the candidates and the one picked by the last Tab
+        let mut completion: Option<(usize, Vec<String>, usize)> = None;
+        let mut browsing: Option<Browsing> = None;
 
         let layout = Layout::default()
             .direction(Direction::Vertical)
@@ -243,100 +314,246 @@ impl Vault<'_> {
                 .draw(|frame| {
                     let chunks = layout.split(frame.area());
 
-                    frame.render_widget(self.tabs[self.current_tab].textarea(), chunks[1]);
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[1],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
 
+                    let mut block = Block::bordered().title("Command");
+                    if let Some((_, candidates, selected)) = &completion {
+                        block = block.title_bottom(completion_line(candidates, *selected));
+                    }
+                    command_area.set_block(block);
                     frame.render_widget(&command_area, chunks[0]);
                 })
                 .unwrap();
 
-            match Self::read()?.into() {
+            let input = Self::read_input()?;
+            if !matches!(input.key, Key::Tab) {
+                completion = None;
+            }
+            if !matches!(input.key, Key::Up | Key::Down) {
+                browsing = None;
+            }
+
+            match input {
                 Input { key: Key::Esc, .. } => break,
                 Input {
                     key: Key::Enter, ..
                 } => {
-                    let input = command_area.lines();
-                    self.exec_command(Command::str_to_command(&input[0]))?;
+                    let line = command_area.lines()[0].clone();
+                    let result = self.command_history.push(&line);
+                    self.handle_error(result);
+                    let result =
+                        ExCommand::parse(&line).and_then(|command| self.exec_ex_command(command));
+                    self.handle_error(result);
                     break;
                 }
+                Input {
+                    key: Key::Tab,
+                    shift,
+                    ..
+                } => {
+                    let line = command_area.lines()[0].clone();
+                    let (start, candidates, selected) = match completion.take() {
+                        Some((start, candidates, selected)) => {
+                            let len = candidates.len();
+                            let selected = if shift {
+                                (selected + len - 1) % len
+                            } else {
+                                (selected + 1) % len
+                            };
+                            (start, candidates, selected)
+                        }
+                        None => {
+                            let (start, candidates) = self.completions(&line);
+                            if candidates.is_empty() {
+                                continue;
+                            }
+                            let selected = if shift { candidates.len() - 1 } else { 0 };
+                            (start, candidates, selected)
+                        }
+                    };
+                    set_line(
+                        &mut command_area,
+                        &(line[..start].to_string() + &candidates[selected]),
+                    );
+                    completion = Some((start, candidates, selected));
+                }
+                Input { key: Key::Up, .. } => {
+                    let browsing =
+                        browsing.get_or_insert_with(|| Browsing::new(&command_area.lines()[0]));
+                    if let Some(entry) = browsing.older(&self.command_history) {
+                        set_line(&mut command_area, entry);
+                    }
+                }
+                Input { key: Key::Down, .. } => {
+                    if let Some(entry) = browsing
+                        .as_mut()
+                        .and_then(|browsing| browsing.newer(&self.command_history))
+                    {
+                        set_line(&mut command_area, &entry);
+                    }
+                }
                 input => {
                     command_area.input(input);
                 }
             }
         }
 
-        Ok(Vim::new(Mode::Normal))
+        Ok(self.vim.with_mode(Mode::Normal))
     }
 
-    fn render_autocomplete(&mut self) -> Result<String, VaultError> {
-        let scores = {
-            let mut scores: Vec<(String, i64)> = Vec::new();
-            let editor = &self.tabs[self.current_tab];
-            let (row, _) = editor.textarea().cursor();
-            let lines = editor.textarea().lines();
-
-            let matcher = SkimMatcherV2::default();
-            let start = lines[row].to_string().find("[[").unwrap() + 2;
-            for file in &self.file_paths {
-                let to_match = &lines[row][start..];
-                let matched = matcher.fuzzy_match(file.to_str().unwrap(), to_match);
-                if matched.is_some() {
-                    scores.push((file.to_str().unwrap().to_string(), matched.unwrap()));
-                } else {
-                    continue;
-                }
-            }
-            scores.sort();
-            scores.reverse();
-            scores
-        };
-
-        let mut autocomplete_area = TextArea::default();
-        autocomplete_area.set_cursor_line_style(Style::default());
-        autocomplete_area.set_block(Block::bordered());
-
-        for (name, _) in scores {
-            autocomplete_area.insert_str(name);
-            autocomplete_area.insert_newline();
-        }
-
-        autocomplete_area.move_cursor(tui_textarea::CursorMove::Jump(0, 0));
-
+    /// The completion popup at the cursor, open while the word before the cursor completes
+    /// to something. Other keys than those picking a completion go on to the note
+    fn render_completion(&mut self) -> Result<(), VaultError> {
         let layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Length(10)].as_ref());
+            .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref());
+        // The candidates are only looked up again when the kind of word changes
+        let mut sources: Option<(Kind, Vec<Candidate>)> = None;
+        let mut selected = 0;
 
         loop {
+            let textarea = self.tabs[self.current_tab].textarea();
+            let (row, col) = textarea.cursor();
+            let Some(token) = complete::token(&textarea.lines()[row], col) else {
+                return Ok(());
+            };
+            if sources.as_ref().is_none_or(|(kind, _)| *kind != token.kind) {
+                sources = Some((token.kind.clone(), self.completion_candidates(&token.kind)));
+            }
+            let candidates = complete::filter(sources.as_ref().unwrap().1.clone(), &token.typed);
+            if candidates.is_empty() {
+                return Ok(());
+            }
+            selected = selected.min(candidates.len() - 1);
+
             self.terminal
                 .draw(|frame| {
                     let chunks = layout.split(frame.area());
-
-                    frame.render_widget(&autocomplete_area, chunks[1]);
-                    frame.render_widget(self.tabs[self.current_tab].textarea(), chunks[0]);
+                    let cursor = self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[0],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
+                    if let Some(cursor) = cursor {
+                        let typed = token.typed.width() as u16;
+                        let at = Position::new(cursor.x.saturating_sub(typed), cursor.y);
+                        let (area, popup) = completion_popup(&candidates, selected, at, chunks[0]);
+                        frame.render_widget(Clear, area);
+                        frame.render_widget(popup, area);
+                    }
                 })
                 .unwrap();
 
-            match Self::read()?.into() {
-                Input { key: Key::Esc, .. } => break,
+            match Self::read_input()? {
+                Input { key: Key::Esc, .. } => return Ok(()),
+                Input { key: Key::Down, .. }
+                | Input {
+                    key: Key::Char('n'),
+                    ctrl: true,
+                    ..
+                } => selected = (selected + 1) % candidates.len(),
+                Input { key: Key::Up, .. }
+                | Input {
+                    key: Key::Char('p'),
+                    ctrl: true,
+                    ..
+                } => selected = (selected + candidates.len() - 1) % candidates.len(),
                 Input {
-                    key: Key::Enter, ..
+                    key: Key::Enter | Key::Tab,
+                    ..
                 }
                 | Input {
                     key: Key::Char('y'),
                     ctrl: true,
                     ..
                 } => {
-                    let (row, _) = autocomplete_area.cursor();
-                    let lines = autocomplete_area.lines();
-
-                    return Ok(lines[row].clone());
+                    // Only the word is replaced, what follows it stays
+                    let current = &mut self.tabs[self.current_tab];
+                    let textarea = &mut current.textareas[current.current];
+                    jump(textarea, (row, token.start));
+                    textarea.start_selection();
+                    jump(textarea, (row, col));
+                    textarea.insert_str(&candidates[selected].text);
+                    return Ok(());
                 }
                 input => {
-                    autocomplete_area.input(input);
+                    // Typing in Insert mode keeps the popup open, anything else closes it
+                    let tab = &mut self.tabs[self.current_tab];
+                    match self
+                        .vim
+                        .exec(input, &mut tab.textareas[tab.current], &self.config)
+                    {
+                        Transition::Mode(Mode::Insert)
+                        | Transition::Nop
+                        | Transition::AutoComplete
+                            if self.vim.mode == Mode::Insert =>
+                        {
+                            selected = 0
+                        }
+                        transition => return self.transition(transition),
+                    }
                 }
             }
         }
+    }
 
-        Ok("".to_string())
+    /// What a word of a kind can complete to
+    fn completion_candidates(&self, kind: &Kind) -> Vec<Candidate> {
+        let textarea = self.tabs[self.current_tab].textarea();
+        match kind {
+            Kind::Note => {
+                let name = |path: &Path| {
+                    let name = path.to_string_lossy();
+                    name.strip_suffix(".md").unwrap_or(&name).to_string()
+                };
+                // An alias links to its note and shows as itself
+                let aliases = self
+                    .vault_aliases()
+                    .into_iter()
+                    .map(|(path, alias)| Candidate {
+                        text: format!("{}|{}", name(&path), alias),
+                        label: alias,
+                        source: "alias",
+                    });
+                self.file_paths
+                    .iter()
+                    .map(|path| Candidate::new(name(path), "note"))
+                    .chain(aliases)
+                    .collect()
+            }
+            Kind::Heading(name) => {
+                // `[[#heading` links to a heading of the note itself
+                let lines = if name.trim().is_empty() {
+                    textarea.lines().to_vec()
+                } else {
+                    let text = fs::read_to_string(note_path(name.trim())).unwrap_or_default();
+                    text.lines().map(str::to_string).collect()
+                };
+                note::headings(&lines)
+                    .into_iter()
+                    .map(|(_, _, heading)| Candidate::new(heading, "heading"))
+                    .collect()
+            }
+            Kind::Alias(name) => {
+                let text = fs::read_to_string(note_path(name.trim())).unwrap_or_default();
+                note::aliases(&text)
+                    .into_iter()
+                    .map(|alias| Candidate::new(alias, "alias"))
+                    .collect()
+            }
+            Kind::Tag => self
+                .vault_tags()
+                .into_iter()
+                .map(|tag| Candidate::new(tag, "tag"))
+                .collect(),
+            Kind::Variable => complete::variables(&note::frontmatter(&textarea.lines().join("\n"))),
+        }
     }
 
     fn render_file_search(&mut self) -> Result<String, VaultError> {
@@ -355,24 +572,41 @@ impl Vault<'_> {
                 .as_ref(),
             );
 
+        let aliases = self.vault_aliases();
         let mut autocomplete_cursor = (0, 0);
         loop {
+            // The score, what is shown and the path of each note found, a note found by an
+            // alias showing it
             let scores = {
-                let mut scores: Vec<(String, i64)> = Vec::new();
+                let mut scores: Vec<(i64, String, String)> = Vec::new();
                 let lines = note_search_area.lines();
 
                 let matcher = SkimMatcherV2::default();
                 for file in &self.file_paths {
                     let to_match = &lines[0];
                     let matched = matcher.fuzzy_match(file.to_str().unwrap(), to_match);
-                    if matched.is_some() {
-                        scores.push((file.to_str().unwrap().to_string(), matched.unwrap()));
-                    } else {
-                        continue;
+                    if let Some(score) = matched {
+                        let file = file.to_str().unwrap().to_string();
+                        scores.push((score, file.clone(), file));
+                    }
+                }
+                for (file, alias) in &aliases {
+                    if let Some(score) = matcher.fuzzy_match(alias, &lines[0]) {
+                        let file = file.to_str().unwrap().to_string();
+                        scores.push((score, format!("{} ({})", alias, file), file));
                     }
                 }
                 scores.sort();
                 scores.reverse();
+                // Each note once, as its best match
+                let mut found = Vec::new();
+                scores.retain(|(_, _, file)| {
+                    if found.contains(file) {
+                        return false;
+                    }
+                    found.push(file.clone());
+                    true
+                });
                 scores
             };
 
@@ -380,8 +614,8 @@ impl Vault<'_> {
             autocomplete_area.set_cursor_line_style(Style::default());
             autocomplete_area.set_block(Block::bordered());
 
-            for (name, _) in scores {
-                autocomplete_area.insert_str(name);
+            for (_, shown, _) in &scores {
+                autocomplete_area.insert_str(shown);
                 autocomplete_area.insert_newline();
             }
 
@@ -396,11 +630,16 @@ impl Vault<'_> {
 
                     frame.render_widget(&note_search_area, chunks[0]);
                     frame.render_widget(&autocomplete_area, chunks[1]);
-                    frame.render_widget(self.tabs[self.current_tab].textarea(), chunks[2]);
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[2],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
                 })
                 .unwrap();
 
-            match Self::read()?.into() {
+            match Self::read_input()? {
                 Input { key: Key::Esc, .. } => break,
                 Input {
                     key: Key::Enter, ..
@@ -411,9 +650,10 @@ impl Vault<'_> {
                     ..
                 } => {
                     let (row, _) = autocomplete_area.cursor();
-                    let lines = autocomplete_area.lines();
-
-                    return Ok(lines[row].clone());
+                    return Ok(scores
+                        .get(row)
+                        .map(|(_, _, file)| file.clone())
+                        .unwrap_or_default());
                 }
                 input => {
                     if input
@@ -449,8 +689,6 @@ impl Vault<'_> {
         let mut search_area = TextArea::default();
         search_area.set_cursor_line_style(Style::default());
 
-        search_area.set_block(Block::bordered().title("Search"));
-
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
@@ -465,43 +703,103 @@ impl Vault<'_> {
                 .as_ref(),
             );
 
+        let mut browsing: Option<Browsing> = None;
+        // Shown under the search while the pattern does not compile
+        let mut error: Option<String> = None;
         loop {
+            let mut block = Block::bordered().title("Search");
+            if let Some(error) = &error {
+                block = block
+                    .title_bottom(Span::styled(error.clone(), Style::default().fg(Color::Red)));
+            }
+            search_area.set_block(block);
+
             self.terminal
                 .draw(|frame| {
                     let chunks = layout.split(frame.area());
-                    let tab = &self.tabs[self.current_tab];
-
-                    frame.render_widget(&tab.textareas[tab.current], chunks[1]);
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[1],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
                     frame.render_widget(&search_area, chunks[0]);
                 })
                 .unwrap();
 
+            let input = Self::read_input()?;
+            if !matches!(input.key, Key::Up | Key::Down) {
+                browsing = None;
+            }
+
             let textarea: &mut TextArea = {
                 let tab = &mut self.tabs[self.current_tab];
                 &mut tab.textareas[tab.current]
             };
 
-            match Self::read()?.into() {
+            match input {
                 Input {
                     key: Key::Enter, ..
                 } => {
-                    textarea.search_forward(true);
+                    let line = search_area.lines()[0].clone();
+                    if let Some(error) = error {
+                        let _ = textarea.set_search_pattern(previous_search);
+                        return Err(VaultError::Command(error));
+                    }
+                    let found = textarea.search_forward(true) || line.is_empty();
+                    self.search_history.push(&line)?;
+                    if !found {
+                        return Err(VaultError::Command(format!("Pattern not found: {}", line)));
+                    }
                     break;
                 }
                 Input { key: Key::Esc, .. } => {
-                    textarea.set_search_pattern(previous_search).unwrap();
+                    let _ = textarea.set_search_pattern(previous_search);
                     break;
                 }
+                Input { key: Key::Up, .. } => {
+                    let browsing =
+                        browsing.get_or_insert_with(|| Browsing::new(&search_area.lines()[0]));
+                    if let Some(entry) = browsing.older(&self.search_history) {
+                        set_line(&mut search_area, entry);
+                    }
+                }
+                Input { key: Key::Down, .. } => {
+                    if let Some(entry) = browsing
+                        .as_mut()
+                        .and_then(|browsing| browsing.newer(&self.search_history))
+                    {
+                        set_line(&mut search_area, &entry);
+                    }
+                }
                 input => {
                     search_area.input(input);
-                    let lines = search_area.lines();
-                    textarea.set_search_pattern(lines[0].clone()).unwrap();
+                }
+            }
+
+            // Search as the pattern is typed or picked from the history
+            let textarea: &mut TextArea = {
+                let tab = &mut self.tabs[self.current_tab];
+                &mut tab.textareas[tab.current]
+            };
+            let pattern = search_regex(&search_area.lines()[0], &self.config);
+            match textarea.set_search_pattern(pattern) {
+                Ok(()) => {
+                    error = None;
                     textarea.search_forward(true);
                 }
+                Err(regex_error) => {
+                    // Only the last line of the message says what is wrong
+                    let message = regex_error.to_string();
+                    let reason = message.lines().last().unwrap_or_default();
+                    error = Some(format!(
+                        "Invalid pattern: {}",
+                        reason.trim_start_matches("error: ")
+                    ));
+                }
             }
         }
-        search_area.set_search_pattern("").unwrap();
-        Ok(Vim::new(Mode::Normal))
+        Ok(self.vim.with_mode(Mode::Normal))
     }
 
     fn render_notification_area(&mut self, notification: String) -> Result<(), VaultError> {
@@ -525,17 +823,14 @@ impl Vault<'_> {
                         tab.current -= 1;
                     }
 
-                    frame.render_widget(&tab.textareas[tab.current], frame.area());
+                    tab.render(frame, frame.area(), self.vim.mode, self.config.conceal);
                     frame.render_widget(Clear, rect);
                     frame.render_widget(&notification_area, rect);
                 })
                 .unwrap();
 
-            match Self::read()?.into() {
-                Input { key: Key::Esc, .. } => {
-                    break;
-                }
-                _ => (),
+            if let Input { key: Key::Esc, .. } = Self::read_input()? {
+                break;
             }
         }
 
@@ -543,68 +838,147 @@ impl Vault<'_> {
     }
 
     fn new_note(&mut self) -> Result<(), VaultError> {
-        let mut note_name_area = TextArea::default();
-        note_name_area.set_cursor_line_style(Style::default());
-        note_name_area.set_block(Block::bordered().title("Note Name"));
+        let Some(title) = self.render_prompt("Note Name")? else {
+            return Ok(());
+        };
+        self.create_note(&title, None)
+    }
 
-        let layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    // Layout for note name area
-                    // As tall as the cursor
-                    Constraint::Length(3),
-                    // The area for the editor
-                    // Takes up the rest of the area
-                    Constraint::Min(1),
-                ]
-                .as_ref(),
-            );
+    fn new_note_from_template(&mut self, template: Option<PathBuf>) -> Result<(), VaultError> {
+        let Some(title) = self.render_prompt("Note Name")? else {
+            return Ok(());
+        };
 
-        loop {
-            self.terminal
-                .draw(|frame| {
-                    let chunks = layout.split(frame.area());
+        let folder = Path::new(&title).parent().unwrap_or(Path::new(""));
+        let template = template.or_else(|| self.config.folder_template(folder).map(PathBuf::from));
+        let template = match template {
+            Some(template) => template,
+            None => match self.pick_template()? {
+                Some(template) => template,
+                None => return Ok(()),
+            },
+        };
 
-                    let tab = &self.tabs[self.current_tab];
-                    frame.render_widget(&tab.textareas[tab.current], chunks[1]);
+        self.create_note(&title, Some(template))
+    }
 
-                    frame.render_widget(&note_name_area, chunks[0]);
-                })
-                .unwrap();
+    /// Create a note named by the naming scheme in the folder of the title, and open it.
+    /// Without a template it starts from the default template of its folder, if there is one
+    fn create_note(&mut self, title: &str, template: Option<PathBuf>) -> Result<(), VaultError> {
+        // Make it so the user does not need to provide the file extension
+        let title = Path::new(title.trim().trim_end_matches(".md"));
+        let folder = title.parent().unwrap_or(Path::new(""));
+        let name = title.file_name().unwrap_or_default().to_str().unwrap();
 
-            match Self::read()?.into() {
-                Input {
-                    key: Key::Enter, ..
-                } => {
-                    // Make it so the user does not need to provide the file extension
-                    note_name_area.insert_str(".md");
+        let (filename, _) = Template::parse(&self.config.note_name)?.expand(&Context::new(name))?;
+        let path = folder.join(filename + ".md");
 
-                    let filename = get_formated_date("{{date:YMMDDHHmm-}}".to_string())
-                        + &note_name_area.lines()[0];
+        self.create_file(path, name, template)
+    }
 
-                    let pathbuf = PathBuf::from(filename);
+    /// Open the daily note of a date, creating it when there is none yet
+    fn open_daily_note(&mut self, date: NaiveDate) -> Result<(), VaultError> {
+        let path = self.config.daily_note(date);
+        let title = path.with_extension("");
+        let title = title.file_name().unwrap_or_default().to_str().unwrap();
+        self.create_file(path, title, None)
+    }
 
-                    File::create(&pathbuf).unwrap();
-                    self.open_file(pathbuf.clone())?;
-                    self.file_paths.push(pathbuf);
+    /// Open the note a link like `note` or `folder/note#heading` points to, creating a
+    /// daily note that does not exist yet
+    fn follow_link(&mut self, target: &str) -> Result<(), VaultError> {
+        let (filename, section) = match target.split_once('#') {
+            Some((filename, section)) => (filename.trim(), Some(section)),
+            None => (target.trim(), None),
+        };
+        let mut path = note_path(filename);
+        // A link may name a note by one of its aliases
+        if !path.exists()
+            && let Some((aliased, _)) = self
+                .vault_aliases()
+                .into_iter()
+                .find(|(_, alias)| alias.eq_ignore_ascii_case(filename))
+        {
+            path = aliased;
+        }
+        match self.config.daily_date(filename) {
+            Some(date) if !path.exists() => self.open_daily_note(date)?,
+            _ => self.open_file(path)?,
+        }
 
-                    break;
-                }
-                Input { key: Key::Esc, .. } => break,
-                input => {
-                    note_name_area.input(input);
-                }
-            }
+        // The cursor goes to the heading or block the link names, when there is one
+        let tab = &mut self.tabs[self.current_tab];
+        let textarea = &mut tab.textareas[tab.current];
+        if let Some(rows) = section.and_then(|section| note::section(textarea.lines(), section)) {
+            let col = first_non_blank(&textarea.lines()[rows.start]);
+            jump(textarea, (rows.start, col));
+        }
+        Ok(())
+    }
+
+    /// Open the note embedded on the cursor line, at the section it embeds. With several
+    /// embeds on the line, the one under or after the cursor
+    fn open_embed(&mut self) -> Result<(), VaultError> {
+        let (row, col) = self.tabs[self.current_tab].textarea().cursor();
+        let line = &self.tabs[self.current_tab].textarea().lines()[row];
+        let col = line
+            .char_indices()
+            .nth(col)
+            .map_or(line.len(), |(index, _)| index);
+        let embeds: Vec<_> = note::EMBED.captures_iter(line).collect();
+        let embed = embeds
+            .iter()
+            .find(|embed| embed.get(0).unwrap().end() > col)
+            .or(embeds.last())
+            .ok_or_else(|| VaultError::Command("No embed on this line".to_string()))?;
+        let target = embed[1].to_string();
+        self.follow_link(&target)
+    }
+
+    /// Open a note, and when it does not exist yet create it from the template
+    /// or from the default template of its folder
+    fn create_file(
+        &mut self,
+        path: PathBuf,
+        title: &str,
+        template: Option<PathBuf>,
+    ) -> Result<(), VaultError> {
+        let folder = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let error =
+            || VaultError::OpenFile("Failed to create: ".to_string() + path.to_str().unwrap());
+        let created = !path.exists();
+        if created {
+            fs::create_dir_all(&folder).map_err(|_| error())?;
+            File::create(&path).map_err(|_| error())?;
+        }
+        self.open_file(path.clone())?;
+        if !self.file_paths.contains(&path) {
+            self.file_paths.push(path);
+        }
+
+        let template = template.or_else(|| self.config.folder_template(&folder).map(PathBuf::from));
+        if let Some(template) = template
+            && created
+        {
+            self.expand_template(template, Some(title))?;
+            self.tabs[self.current_tab].save()?;
         }
 
         Ok(())
     }
 
-    fn insert_template(&mut self) -> Result<String, VaultError> {
+    /// Fuzzy find a note of the templates folder
+    fn pick_template(&mut self) -> Result<Option<PathBuf>, VaultError> {
+        if self.template_paths().is_empty() {
+            return Err(VaultError::Command(format!(
+                "No templates in {}/, set another folder with :set templates=FOLDER",
+                self.config.templates
+            )));
+        }
+
         let mut template_name_area = TextArea::default();
         template_name_area.set_cursor_line_style(Style::default());
-        template_name_area.set_block(Block::bordered().title("Note Search"));
+        template_name_area.set_block(Block::bordered().title("Template Search"));
 
         let layout = Layout::default()
             .direction(Direction::Vertical)
@@ -624,13 +998,11 @@ impl Vault<'_> {
                 let lines = template_name_area.lines();
 
                 let matcher = SkimMatcherV2::default();
-                for file in &self.file_paths {
+                for file in self.template_paths() {
                     let to_match = &lines[0];
                     let matched = matcher.fuzzy_match(file.to_str().unwrap(), to_match);
-                    if matched.is_some() {
-                        scores.push((file.to_str().unwrap().to_string(), matched.unwrap()));
-                    } else {
-                        continue;
+                    if let Some(score) = matched {
+                        scores.push((file.to_str().unwrap().to_string(), score));
                     }
                 }
                 scores.sort();
@@ -658,11 +1030,16 @@ impl Vault<'_> {
 
                     frame.render_widget(&template_name_area, chunks[0]);
                     frame.render_widget(&autocomplete_area, chunks[1]);
-                    frame.render_widget(self.tabs[self.current_tab].textarea(), chunks[2]);
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[2],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
                 })
                 .unwrap();
 
-            match Self::read()?.into() {
+            match Self::read_input()? {
                 Input {
                     key: Key::Enter, ..
                 }
@@ -674,32 +1051,10 @@ impl Vault<'_> {
                     let (row, _) = autocomplete_area.cursor();
                     let lines = autocomplete_area.lines();
 
-                    let pathbuf = PathBuf::from(&lines[row]);
-
-                    self.file_paths.push(pathbuf.clone());
-
-                    let template = Self::open_template(pathbuf.clone())?;
-                    let tab = &mut self.tabs[self.current_tab];
-
-                    let lines = template.textarea().lines();
-
-                    for line in lines {
-                        let mut line = line.to_string();
-                        if line.contains("{{title}}") {
-                            let inner = line.replace("{{title}}", tab.path().to_str().unwrap());
-                            line = inner;
-                        }
-
-                        if line.contains("{{date:") {
-                            let inner = get_formated_date(line.to_string());
-                            line = inner;
-                        }
-
-                        tab.textareas[tab.current].insert_str(line);
-                        tab.textareas[tab.current].insert_newline();
+                    if lines[row].is_empty() {
+                        continue;
                     }
-
-                    break;
+                    return Ok(Some(PathBuf::from(&lines[row])));
                 }
                 Input { key: Key::Esc, .. } => break,
                 input => {
@@ -729,71 +1084,1130 @@ impl Vault<'_> {
             }
         }
 
-        Ok("".to_string())
+        Ok(None)
     }
 
-    fn exec_command(&mut self, command: Command) -> Result<(), VaultError> {
-        match command {
-            Command::Quit => {
-                self.tabs.remove(self.current_tab);
-                if self.tabs.len() == 0 {
-                    self.run = false;
-                } else {
-                    if self.current_tab >= self.tabs.len() && self.current_tab != 0 {
-                        self.current_tab -= 1;
-                    }
-                }
-            }
-            Command::Save => {
-                self.tabs[self.current_tab].save()?;
-            }
-            Command::SaveQuit => {
-                self.tabs[self.current_tab].save()?;
-                self.tabs.remove(self.current_tab);
-                if self.tabs.len() == 0 {
-                    self.run = false;
-                } else {
-                    if self.current_tab >= self.tabs.len() {
-                        self.current_tab -= 1;
-                    }
-                }
-            }
-            Command::NewNote => {
-                let result = self.new_note();
-                self.handle_error(result);
-            }
-            Command::FollowLink => {
-                let tab = &self.tabs[self.current_tab];
-                let (row, col) = tab.textarea().cursor();
-                let current_line = &tab.textarea().lines()[row];
+    /// Insert a template at the cursor, asking for its prompts and filling in its variables.
+    /// `{{title}}` is the given title, or else the name of the note
+    fn expand_template(&mut self, path: PathBuf, title: Option<&str>) -> Result<(), VaultError> {
+        // Templates can be named from the templates folder
+        let path = if path.exists() {
+            path
+        } else {
+            Path::new(&self.config.templates).join(path)
+        };
+        let text = fs::read_to_string(&path).map_err(|_| {
+            VaultError::OpenFile("Failed to open: ".to_string() + path.to_str().unwrap())
+        })?;
+        let template = Template::parse(&text)?;
+
+        let tab = &self.tabs[self.current_tab];
+        let title =
+            title.unwrap_or_else(|| tab.path().file_stem().unwrap_or_default().to_str().unwrap());
+        let mut context = Context::new(title);
+        // The note's own frontmatter wins over the template's
+        let note = tab.textarea().lines().join("\n");
+        for (key, values) in note::frontmatter(&text)
+            .into_iter()
+            .chain(note::frontmatter(&note))
+        {
+            context.fields.insert(key, values);
+        }
 
-                let col = if col + 1 > current_line.len() {
-                    col
-                } else {
-                    col + 1
-                };
+        for label in template.prompts() {
+            let Some(answer) = self.render_prompt(&label)? else {
+                return Ok(());
+            };
+            context.answers.insert(label, answer);
+        }
 
-                let line_split = current_line.split_at(col);
+        let (mut text, cursor) = template.expand(&context)?;
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
 
-                if line_split.0.contains("[[") && !line_split.0.contains("]]") {
-                    let bracket_start_idx = current_line.find("[[").unwrap() + 2;
-                    let bracket_end_idx = current_line.find("]]").unwrap();
-                    let inside_filename = &current_line[bracket_start_idx..bracket_end_idx];
+        let tab = &mut self.tabs[self.current_tab];
+        let textarea = &mut tab.textareas[tab.current];
+        let (row, col) = textarea.cursor();
+        textarea.insert_str(text);
+        if let Some((cursor_row, cursor_col)) = cursor {
+            let cursor_col = if cursor_row == 0 {
+                col + cursor_col
+            } else {
+                cursor_col
+            };
+            jump(textarea, (row + cursor_row, cursor_col));
+        }
 
-                    let filename = inside_filename.split("|").collect::<Vec<&str>>()[0];
-                    let result = self.open_file(PathBuf::from(filename.to_string() + ".md"));
-                    self.handle_error(result);
-                }
-            }
-            Command::InsertTemplate => {
-                self.file_paths = get_all_filenames(true).unwrap();
-                let result = self.insert_template();
+        Ok(())
+    }
+
+    /// Ask for a line of text, which is None when the user cancels with Esc
+    fn render_prompt(&mut self, title: &str) -> Result<Option<String>, VaultError> {
+        let mut prompt_area = TextArea::default();
+        prompt_area.set_cursor_line_style(Style::default());
+        prompt_area.set_block(Block::bordered().title(title.to_string()));
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref());
+
+        loop {
+            self.terminal
+                .draw(|frame| {
+                    let chunks = layout.split(frame.area());
+
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[1],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
+                    frame.render_widget(&prompt_area, chunks[0]);
+                })
+                .unwrap();
+
+            match Self::read_input()? {
+                Input {
+                    key: Key::Enter, ..
+                } => return Ok(Some(prompt_area.lines()[0].clone())),
+                Input { key: Key::Esc, .. } => return Ok(None),
+                input => {
+                    prompt_area.input(input);
+                }
+            }
+        }
+    }
+
+    /// The notes of the templates folder
+    fn template_paths(&self) -> Vec<PathBuf> {
+        self.file_paths
+            .iter()
+            .filter(|path| path.starts_with(&self.config.templates))
+            .cloned()
+            .collect()
+    }
+
+    /// The notes of the vault: its Markdown files, leaving out hidden folders like `.git`
+    /// and `.obsidian`
+    fn note_paths(&self) -> Vec<PathBuf> {
+        self.file_paths
+            .iter()
+            .filter(|path| is_note(path))
+            .cloned()
+            .collect()
+    }
+
+    /// The aliases given in the frontmatter of the notes of the vault, with their note
+    fn vault_aliases(&self) -> Vec<(PathBuf, String)> {
+        let mut aliases = Vec::new();
+        for path in &self.note_paths() {
+            if let Ok(text) = fs::read_to_string(path) {
+                aliases.extend(
+                    note::aliases(&text)
+                        .into_iter()
+                        .map(|alias| (path.clone(), alias)),
+                );
+            }
+        }
+        aliases
+    }
+
+    /// Every tag used in the vault
+    fn vault_tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+        for path in &self.note_paths() {
+            if let Ok(text) = fs::read_to_string(path) {
+                tags.extend(note::tags(&text).map(str::to_string));
+            }
+        }
+        tags
+    }
+
+    /// The start of the word Tab completes in a command line, and what it can become
+    fn completions(&self, input: &str) -> (usize, Vec<String>) {
+        let (start, completion) = completion_target(input);
+        let word = &input[start..];
+
+        let paths = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.to_str().unwrap().to_string())
+                .collect()
+        };
+        let mut candidates = match completion {
+            _ if word.starts_with('#') => self.vault_tags(),
+            Completion::Command => COMMANDS
+                .iter()
+                .flat_map(|command| command.names)
+                .map(|name| name.to_string())
+                .collect(),
+            Completion::Argument(Argument::Path) => paths(self.file_paths.clone()),
+            Completion::Argument(Argument::Template) => paths(self.template_paths()),
+            Completion::Argument(Argument::Option) => {
+                OPTIONS.iter().map(|option| option.to_string()).collect()
+            }
+            Completion::Argument(Argument::Date) => {
+                PHRASE_WORDS.iter().map(|word| word.to_string()).collect()
+            }
+            Completion::Argument(Argument::Table) => table::ACTIONS
+                .iter()
+                .map(|action| action.to_string())
+                .collect(),
+            Completion::Argument(Argument::Callout) => {
+                CALLOUTS.iter().map(|(kind, _)| kind.to_string()).collect()
+            }
+            Completion::Argument(_) => Vec::new(),
+        };
+
+        // Notes deep in folders are found by their file name as well
+        candidates.retain(|candidate| {
+            candidate.starts_with(word)
+                || Path::new(candidate)
+                    .file_name()
+                    .is_some_and(|name| name.to_str().unwrap().starts_with(word))
+        });
+        candidates.sort();
+        candidates.dedup();
+
+        (start, candidates)
+    }
+
+    fn render_command_palette(&mut self) -> Result<(), VaultError> {
+        let mut palette_area = TextArea::default();
+        palette_area.set_cursor_line_style(Style::default());
+        palette_area.set_block(Block::bordered().title("Command Palette"));
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    // At most ten commands show at once, the list scrolling to the selected
+                    Constraint::Length(COMMANDS.len().min(10) as u16 + 2),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            );
+        let usage_width = COMMANDS.iter().map(|command| command.usage.len()).max();
+        let keys_width = COMMANDS.iter().map(|command| command.keys.len()).max();
+
+        let mut state = TableState::default().with_selected(0);
+        loop {
+            let matches = {
+                let matcher = SkimMatcherV2::default();
+                let query = &palette_area.lines()[0];
+                let mut matches: Vec<(i64, usize)> = COMMANDS
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, command)| {
+                        let text = format!(
+                            "{} {} {}",
+                            command.usage,
+                            command.names.join(" "),
+                            command.description
+                        );
+                        matcher
+                            .fuzzy_match(&text, query)
+                            .map(|score| (-score, index))
+                    })
+                    .collect();
+                matches.sort();
+                matches
+            };
+            if let Some(selected) = state.selected() {
+                state.select(Some(selected.min(matches.len().saturating_sub(1))));
+            }
+
+            let rows = matches.iter().map(|&(_, index)| {
+                let command = &COMMANDS[index];
+                Row::new([
+                    Cell::from(command.usage),
+                    Cell::from(command.description),
+                    Cell::from(command.keys),
+                ])
+            });
+            let table = Table::new(
+                rows,
+                [
+                    Constraint::Length(usage_width.unwrap_or(0) as u16),
+                    Constraint::Min(1),
+                    Constraint::Length(keys_width.unwrap_or(0) as u16),
+                ],
+            )
+            .column_spacing(2)
+            .block(Block::bordered())
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+            self.terminal
+                .draw(|frame| {
+                    let chunks = layout.split(frame.area());
+
+                    frame.render_widget(&palette_area, chunks[0]);
+                    frame.render_stateful_widget(&table, chunks[1], &mut state);
+                    self.tabs[self.current_tab].render(
+                        frame,
+                        chunks[2],
+                        self.vim.mode,
+                        self.config.conceal,
+                    );
+                })
+                .unwrap();
+
+            match Self::read_input()? {
+                Input { key: Key::Esc, .. } => break,
+                Input {
+                    key: Key::Enter, ..
+                } => {
+                    let Some(&(_, index)) = state.selected().and_then(|row| matches.get(row))
+                    else {
+                        continue;
+                    };
+                    let command = &COMMANDS[index];
+                    let name = command.names.first().copied().unwrap_or("");
+
+                    if command.required {
+                        let initial = match command.argument {
+                            Argument::Pattern => format!("{}/", name),
+                            _ if name.is_empty() => String::new(),
+                            _ => format!("{} ", name),
+                        };
+                        self.vim = self.render_command_area(&initial)?;
+                    } else {
+                        let result = ExCommand::parse(name)
+                            .and_then(|command| self.exec_ex_command(command));
+                        self.handle_error(result);
+                    }
+                    break;
+                }
+                Input { key: Key::Up, .. }
+                | Input {
+                    key: Key::Char('p'),
+                    ctrl: true,
+                    ..
+                } => state.select_previous(),
+                Input { key: Key::Down, .. }
+                | Input {
+                    key: Key::Char('n'),
+                    ctrl: true,
+                    ..
+                } => state.select_next(),
+                input => {
+                    palette_area.input(input);
+                    state.select(Some(0));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The tab and buffer a note is open in
+    fn find_buffer(&self, path: &Path) -> Option<(usize, usize)> {
+        self.tabs.iter().enumerate().find_map(|(tab, editor)| {
+            editor
+                .paths
+                .iter()
+                .position(|open| open == path)
+                .map(|buffer| (tab, buffer))
+        })
+    }
+
+    /// The lines of a note, as they are in its buffer when it is open.
+    /// None when it cannot be read as text, as then it is not a note
+    fn note_lines(&self, path: &Path) -> Option<Vec<String>> {
+        match self.find_buffer(path) {
+            Some((tab, buffer)) => Some(self.tabs[tab].textareas[buffer].lines().to_vec()),
+            None => fs::read_to_string(path)
+                .ok()
+                .map(|text| text.lines().map(str::to_string).collect()),
+        }
+    }
+
+    /// Replace a pattern across the vault. Open notes are searched as they are in their
+    /// buffer, and saved with it when changed
+    fn replace_in_vault(&mut self, substitute: Substitute) -> Result<(), VaultError> {
+        let regex = substitute_regex(
+            self.tabs[self.current_tab].textarea(),
+            &substitute.pattern,
+            substitute.ignore_case,
+        )?;
+        self.file_paths = get_all_filenames(true).unwrap();
+
+        let mut hunks = Vec::new();
+        for path in &self.note_paths() {
+            let Some(lines) = self.note_lines(path) else {
+                continue;
+            };
+            hunks.extend(replace::find_hunks(
+                path,
+                &lines,
+                &regex,
+                &substitute.replacement,
+                substitute.all,
+            ));
+        }
+
+        if hunks.is_empty() {
+            return Err(VaultError::Command(format!(
+                "Pattern not found: {}",
+                regex.as_str()
+            )));
+        }
+
+        if self.render_replace_preview(&mut hunks)? {
+            self.apply_replace(&hunks)?;
+        }
+        Ok(())
+    }
+
+    /// Let the user accept or reject every hunk, returning whether to apply them
+    fn render_replace_preview(&mut self, hunks: &mut [Hunk]) -> Result<bool, VaultError> {
+        let mut selected = 0;
+        let mut top = 0;
+
+        loop {
+            let mut lines = Vec::new();
+            // The lines of the selected hunk, to keep them on screen
+            let mut selected_lines = 0..0;
+            for (index, hunk) in hunks.iter().enumerate() {
+                if index == 0 || hunks[index - 1].path != hunk.path {
+                    if index != 0 {
+                        lines.push(Line::default());
+                    }
+                    let changes = hunks.iter().filter(|other| other.path == hunk.path).count();
+                    lines.push(Line::styled(
+                        format!("{} ({} changes)", hunk.path.display(), changes),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ));
+                }
+
+                let start = lines.len();
+                let style = if index == selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                let mark = if hunk.accepted { "[x]" } else { "[ ]" };
+                lines.push(Line::styled(
+                    format!("{} line {}", mark, hunk.start + 1),
+                    style,
+                ));
+                for old in &hunk.old {
+                    lines.push(Line::styled(
+                        format!("  - {}", old),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                for new in &hunk.new {
+                    lines.push(Line::styled(
+                        format!("  + {}", new),
+                        Style::default().fg(Color::Green),
+                    ));
+                }
+                if index == selected {
+                    selected_lines = start..lines.len();
+                }
+            }
+
+            let accepted = hunks.iter().filter(|hunk| hunk.accepted).count();
+            let block = Block::bordered()
+                .title(format!(
+                    "Replace: {}/{} changes accepted",
+                    accepted,
+                    hunks.len()
+                ))
+                .title_bottom(
+                    "j/k move  y/n accept/reject  Space toggle  a/r whole note  A/R all  Enter apply  Esc cancel",
+                );
+
+            self.terminal
+                .draw(|frame| {
+                    let height = frame.area().height.saturating_sub(2) as usize;
+                    if selected_lines.start < top {
+                        top = selected_lines.start;
+                    } else if selected_lines.end > top + height {
+                        top = (selected_lines.end - height).min(selected_lines.start);
+                    }
+
+                    frame.render_widget(
+                        Paragraph::new(lines).block(block).scroll((top as u16, 0)),
+                        frame.area(),
+                    );
+                })
+                .unwrap();
+
+            let path = hunks[selected].path.clone();
+            match Self::read_input()? {
+                Input {
+                    key: Key::Esc | Key::Char('q'),
+                    ..
+                } => return Ok(false),
+                Input {
+                    key: Key::Enter, ..
+                } => return Ok(true),
+                Input {
+                    key: Key::Char('j') | Key::Down,
+                    ..
+                } => selected = (selected + 1).min(hunks.len() - 1),
+                Input {
+                    key: Key::Char('k') | Key::Up,
+                    ..
+                } => selected = selected.saturating_sub(1),
+                Input {
+                    key: Key::Char(c @ ('y' | 'n')),
+                    ..
+                } => {
+                    hunks[selected].accepted = c == 'y';
+                    selected = (selected + 1).min(hunks.len() - 1);
+                }
+                Input {
+                    key: Key::Char(' '),
+                    ..
+                } => hunks[selected].accepted = !hunks[selected].accepted,
+                Input {
+                    key: Key::Char(c @ ('a' | 'r')),
+                    ..
+                } => {
+                    for hunk in hunks.iter_mut().filter(|hunk| hunk.path == path) {
+                        hunk.accepted = c == 'a';
+                    }
+                }
+                Input {
+                    key: Key::Char(c @ ('A' | 'R')),
+                    ..
+                } => {
+                    for hunk in hunks.iter_mut() {
+                        hunk.accepted = c == 'A';
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Write every note with an accepted hunk, or none of them if one fails
+    fn apply_replace(&mut self, hunks: &[Hunk]) -> Result<(), VaultError> {
+        let mut paths: Vec<&PathBuf> = hunks
+            .iter()
+            .filter(|hunk| hunk.accepted)
+            .map(|hunk| &hunk.path)
+            .collect();
+        paths.dedup();
+        // Keep what undoes the last replace when this one changes nothing
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        // Read everything before writing anything, so a failure can put every note back
+        let mut backups = Vec::new();
+        let mut changes = Vec::new();
+        for path in paths {
+            let disk = fs::read(path).map_err(|_| {
+                VaultError::OpenFile("Failed to read: ".to_string() + path.to_str().unwrap())
+            })?;
+            let buffer = self
+                .find_buffer(path)
+                .map(|(tab, buffer)| self.tabs[tab].textareas[buffer].lines().to_vec());
+            let lines = buffer.clone().unwrap_or_else(|| {
+                String::from_utf8_lossy(&disk)
+                    .lines()
+                    .map(str::to_string)
+                    .collect()
+            });
+
+            let note_hunks: Vec<&Hunk> = hunks.iter().filter(|hunk| &hunk.path == path).collect();
+            changes.push(replace::apply(&lines, &note_hunks));
+            backups.push(Backup {
+                path: path.clone(),
+                disk,
+                buffer,
+            });
+        }
+
+        for (index, (backup, lines)) in backups.iter().zip(&changes).enumerate() {
+            if write_file(&backup.path, &contents(lines)).is_err() {
+                let unrestored: Vec<String> = backups[..index]
+                    .iter()
+                    .filter(|written| write_file(&written.path, &written.disk).is_err())
+                    .map(|written| written.path.display().to_string())
+                    .collect();
+                return Err(VaultError::Command(if unrestored.is_empty() {
+                    format!(
+                        "Failed to write {}, no note was changed",
+                        backup.path.display()
+                    )
+                } else {
+                    format!(
+                        "Failed to write {}, and failed to restore {}",
+                        backup.path.display(),
+                        unrestored.join(", ")
+                    )
+                }));
+            }
+        }
+
+        for (backup, lines) in backups.iter().zip(&changes) {
+            if let Some((tab, buffer)) = self.find_buffer(&backup.path) {
+                self.tabs[tab].set_lines(buffer, lines);
+            }
+        }
+        self.last_replace = backups;
+
+        Ok(())
+    }
+
+    /// Read the current note rendered. Links are selected with Tab and followed with Enter,
+    /// the note they open being rendered in turn
+    fn render_preview(&mut self) -> Result<(), VaultError> {
+        let mut top = 0;
+        let mut selected: Option<usize> = None;
+        let mut links: Vec<preview::Link> = Vec::new();
+        let mut height = 0;
+        let mut unfold = false;
+
+        loop {
+            let tab = &self.tabs[self.current_tab];
+            let block = Block::bordered()
+                .title(format!("{} (preview)", tab.path().display()))
+                .title_bottom(
+                    "j/k scroll  Tab/Shift-Tab select link  Enter follow  z open/close folds  Esc close",
+                );
+            let size = self.terminal.size().map_err(|_| VaultError::Input)?;
+            let preview = preview::render(
+                tab.path(),
+                tab.textarea().lines(),
+                size.width.saturating_sub(2) as usize,
+                &|name| {
+                    let path = note_path(name);
+                    self.note_lines(&path).map(|lines| (path, lines))
+                },
+                unfold,
+            );
+
+            self.terminal
+                .draw(|frame| {
+                    let area = frame.area();
+                    height = area.height.saturating_sub(2) as usize;
+
+                    if let Some(link) = selected.and_then(|index| preview.links.get(index)) {
+                        if link.row < top {
+                            top = link.row;
+                        } else if link.row >= top + height {
+                            top = link.row + 1 - height;
+                        }
+                    }
+                    top = top.min(preview.lines.len().saturating_sub(height));
+
+                    frame.render_widget(
+                        Paragraph::new(preview.text(selected))
+                            .block(block)
+                            .scroll((top as u16, 0)),
+                        area,
+                    );
+                    links = preview.links;
+                })
+                .unwrap();
+
+            match Self::read_input()? {
+                Input { key: Key::Esc, .. }
+                | Input {
+                    key: Key::Char('q'),
+                    ..
+                }
+                | Input {
+                    key: Key::Char('e'),
+                    ctrl: true,
+                    ..
+                } => break,
+                Input {
+                    key: Key::Char('d'),
+                    ctrl: true,
+                    ..
+                } => top += height / 2,
+                Input {
+                    key: Key::Char('u'),
+                    ctrl: true,
+                    ..
+                } => top = top.saturating_sub(height / 2),
+                Input {
+                    key: Key::Char('j') | Key::Down,
+                    ..
+                } => top += 1,
+                Input {
+                    key: Key::Char('k') | Key::Up,
+                    ..
+                } => top = top.saturating_sub(1),
+                Input {
+                    key: Key::PageDown, ..
+                } => top += height,
+                Input {
+                    key: Key::PageUp, ..
+                } => top = top.saturating_sub(height),
+                Input {
+                    key: Key::Char('g') | Key::Home,
+                    ..
+                } => top = 0,
+                Input {
+                    key: Key::Char('G') | Key::End,
+                    ..
+                } => top = usize::MAX,
+                Input {
+                    key: Key::Char('z'),
+                    ..
+                } => unfold = !unfold,
+                // Selecting starts from the links in view
+                Input {
+                    key: Key::Tab,
+                    shift: false,
+                    ..
+                }
+                | Input {
+                    key: Key::Char('n'),
+                    ..
+                } if !links.is_empty() => {
+                    selected = Some(match selected {
+                        Some(index) => (index + 1) % links.len(),
+                        None => links.iter().position(|link| link.row >= top).unwrap_or(0),
+                    });
+                }
+                Input { key: Key::Tab, .. }
+                | Input {
+                    key: Key::Char('N'),
+                    ..
+                } if !links.is_empty() => {
+                    selected = Some(match selected {
+                        Some(index) => (index + links.len() - 1) % links.len(),
+                        None => links
+                            .iter()
+                            .rposition(|link| link.row < top + height)
+                            .unwrap_or(links.len() - 1),
+                    });
+                }
+                Input {
+                    key: Key::Enter, ..
+                } => {
+                    let Some(link) = selected.and_then(|index| links.get(index)) else {
+                        continue;
+                    };
+                    let result = self.follow_preview_link(&link.target.clone());
+                    if result.is_ok() {
+                        top = 0;
+                        selected = None;
+                    }
+                    self.handle_error(result);
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Follow a wikilink like the editor does, or a Markdown link to a note of the vault
+    fn follow_preview_link(&mut self, target: &str) -> Result<(), VaultError> {
+        if target.contains("://") || target.starts_with("mailto:") {
+            return Err(VaultError::Command(format!(
+                "Cannot open a link outside of the vault: {}",
+                target
+            )));
+        }
+        self.follow_link(&target.replace("%20", " "))
+    }
+
+    /// The open tasks of the vault, filtered as typed: by note, or as an agenda of the
+    /// overdue, today's and upcoming ones. Enter opens the note at the task, Ctrl-Space
+    /// checks or unchecks it and saves its note
+    fn render_tasks(&mut self, query: &str, agenda: bool) -> Result<(), VaultError> {
+        let today = Local::now().date_naive();
+        self.file_paths = get_all_filenames(true).unwrap();
+        let mut tasks: Vec<Task> = Vec::new();
+        for path in &self.note_paths() {
+            if let Some(lines) = self.note_lines(path) {
+                tasks.extend(
+                    task::tasks(path, &lines)
+                        .into_iter()
+                        .filter(|task| !task.done && (!agenda || task.date().is_some())),
+                );
+            }
+        }
+        if agenda {
+            tasks.sort_by_key(|task| (task.date(), task.priority));
+        }
+        // What a task is listed under
+        let group = |task: &Task| match task.date() {
+            _ if !agenda => task.path.display().to_string(),
+            Some(date) if date < today => "Overdue".to_string(),
+            Some(date) if date == today => "Today".to_string(),
+            _ => "Upcoming".to_string(),
+        };
+
+        let mut filter_area = TextArea::default();
+        filter_area.set_cursor_line_style(Style::default());
+        filter_area.insert_str(query);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref());
+
+        // While a filter is being typed it may not parse, so the last one that did is kept
+        let mut filter = Filter::default();
+        let mut selected = 0;
+        let mut top = 0;
+        loop {
+            let mut filter_block = Block::bordered().title("Filter");
+            match Filter::parse(&filter_area.lines()[0], today) {
+                Ok(parsed) => filter = parsed,
+                Err(error) => {
+                    filter_block = filter_block
+                        .title_bottom(Line::styled(error, Style::default().fg(Color::Red)));
+                }
+            }
+            filter_area.set_block(filter_block);
+
+            let shown: Vec<usize> = (0..tasks.len())
+                .filter(|&index| filter.matches(&tasks[index]))
+                .collect();
+            selected = selected.min(shown.len().saturating_sub(1));
+
+            let mut lines = Vec::new();
+            let mut selected_line = 0;
+            for (position, &index) in shown.iter().enumerate() {
+                let task = &tasks[index];
+                if position == 0 || group(&tasks[shown[position - 1]]) != group(task) {
+                    if position != 0 {
+                        lines.push(Line::default());
+                    }
+                    lines.push(Line::styled(
+                        group(task),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ));
+                }
+
+                let mut style = match task.date() {
+                    Some(date) if date < today && !task.done => Style::default().fg(Color::Red),
+                    _ => Style::default(),
+                };
+                if position == selected {
+                    style = style.add_modifier(Modifier::REVERSED);
+                    selected_line = lines.len();
+                }
+                let mark = if task.done { 'x' } else { ' ' };
+                let line = if agenda {
+                    format!(
+                        "{}  [{}] {}  ({})",
+                        task.date().unwrap().format("%Y-%m-%d"),
+                        mark,
+                        task.text,
+                        task.path.display()
+                    )
+                } else {
+                    format!("{:>5}  [{}] {}", task.row + 1, mark, task.text)
+                };
+                lines.push(Line::styled(line, style));
+            }
+
+            let block = Block::bordered()
+                .title(format!(
+                    "{}: {} shown",
+                    if agenda { "Agenda" } else { "Tasks" },
+                    shown.len()
+                ))
+                .title_bottom(
+                    "Up/Down move  Enter open  Ctrl-Space toggle  Esc close  Filter with #tag, due:DATE, due<DATE, due>DATE, due or text",
+                );
+
+            self.terminal
+                .draw(|frame| {
+                    let chunks = layout.split(frame.area());
+                    let height = chunks[1].height.saturating_sub(2) as usize;
+                    if selected_line < top {
+                        top = selected_line;
+                    } else if selected_line >= top + height {
+                        top = selected_line + 1 - height;
+                    }
+
+                    frame.render_widget(&filter_area, chunks[0]);
+                    frame.render_widget(
+                        Paragraph::new(lines).block(block).scroll((top as u16, 0)),
+                        chunks[1],
+                    );
+                })
+                .unwrap();
+
+            match Self::read_input()? {
+                Input { key: Key::Esc, .. } => break,
+                Input {
+                    key: Key::Enter, ..
+                } => {
+                    let Some(&index) = shown.get(selected) else {
+                        continue;
+                    };
+                    let (path, row) = (tasks[index].path.clone(), tasks[index].row);
+                    // The task was read from the note's buffer when it is open in any tab
+                    match self.find_buffer(&path) {
+                        Some((tab, buffer)) => {
+                            self.current_tab = tab;
+                            self.tabs[tab].current = buffer;
+                        }
+                        None => self.open_file(path)?,
+                    }
+                    let tab = &mut self.tabs[self.current_tab];
+                    let textarea = &mut tab.textareas[tab.current];
+                    let row = row.min(textarea.lines().len() - 1);
+                    let col = first_non_blank(&textarea.lines()[row]);
+                    jump(textarea, (row, col));
+                    break;
+                }
+                Input {
+                    key: Key::Char(' '),
+                    ctrl: true,
+                    ..
+                } => {
+                    let Some(&index) = shown.get(selected) else {
+                        continue;
+                    };
+                    let (path, row) = (tasks[index].path.clone(), tasks[index].row);
+                    let (toggled, warning) = match self.toggle_task_in_note(&path, row) {
+                        Ok(toggled) => toggled,
+                        Err(error) => {
+                            self.handle_error::<()>(Err(error));
+                            continue;
+                        }
+                    };
+                    if let Some(warning) = warning {
+                        self.handle_error::<()>(Err(VaultError::Command(warning)));
+                    }
+
+                    // A next occurrence pushes the tasks below it down a line
+                    for task in tasks.iter_mut() {
+                        if task.path == path && task.row > row {
+                            task.row += toggled.len() - 1;
+                        }
+                    }
+                    tasks.splice(index..=index, toggled);
+                    if agenda {
+                        tasks.sort_by_key(|task| (task.date(), task.priority));
+                    }
+                }
+                Input { key: Key::Up, .. }
+                | Input {
+                    key: Key::Char('p'),
+                    ctrl: true,
+                    ..
+                } => selected = selected.saturating_sub(1),
+                Input { key: Key::Down, .. }
+                | Input {
+                    key: Key::Char('n'),
+                    ctrl: true,
+                    ..
+                } => selected += 1,
+                input => {
+                    filter_area.input(input);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check or uncheck a task of a note and save it, in its buffer too when it is open.
+    /// Returns the task as it now is, followed by its next occurrence when it recurs, and
+    /// why it has none when its rule could not be read
+    fn toggle_task_in_note(
+        &mut self,
+        path: &Path,
+        row: usize,
+    ) -> Result<(Vec<Task>, Option<String>), VaultError> {
+        let mut lines = self.note_lines(path).ok_or_else(|| {
+            VaultError::OpenFile("Failed to read: ".to_string() + path.to_str().unwrap())
+        })?;
+        let (count, warning) = task::toggle(&mut lines, row, Local::now().date_naive())
+            .map_err(VaultError::Command)?;
+
+        write_lines(path, &lines)?;
+        if let Some((tab, buffer)) = self.find_buffer(path) {
+            self.tabs[tab].set_lines(buffer, &lines);
+        }
+        let toggled = task::tasks(path, &lines[row..row + count])
+            .into_iter()
+            .map(|task| Task {
+                row: task.row + row,
+                ..task
+            })
+            .collect();
+        Ok((toggled, warning))
+    }
+
+    fn undo_replace(&mut self) -> Result<(), VaultError> {
+        if self.last_replace.is_empty() {
+            return Err(VaultError::Command("No :replace to undo".to_string()));
+        }
+
+        for backup in std::mem::take(&mut self.last_replace) {
+            write_file(&backup.path, &backup.disk)?;
+
+            if let Some((tab, buffer)) = self.find_buffer(&backup.path) {
+                let lines = backup.buffer.unwrap_or_else(|| {
+                    String::from_utf8_lossy(&backup.disk)
+                        .lines()
+                        .map(str::to_string)
+                        .collect()
+                });
+                self.tabs[tab].set_lines(buffer, &lines);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_command(&mut self, command: Command) -> Result<(), VaultError> {
+        match command {
+            Command::Quit => {
+                self.tabs.remove(self.current_tab);
+                if self.tabs.is_empty() {
+                    self.run = false;
+                } else {
+                    if self.current_tab >= self.tabs.len() && self.current_tab != 0 {
+                        self.current_tab -= 1;
+                    }
+                }
+            }
+            Command::Save(None) => {
+                self.tabs[self.current_tab].save()?;
+            }
+            Command::Save(Some(path)) => {
+                self.tabs[self.current_tab].save_as(&path)?;
+            }
+            Command::Edit(path) => {
+                self.open_file(path)?;
+                // A new note gets a date prefix, so the path typed is not the one opened
+                let tab = &self.tabs[self.current_tab];
+                let opened = tab.paths[tab.current].clone();
+                if !self.file_paths.contains(&opened) {
+                    self.file_paths.push(opened);
+                }
+            }
+            Command::SaveQuit => {
+                self.tabs[self.current_tab].save()?;
+                self.tabs.remove(self.current_tab);
+                if self.tabs.is_empty() {
+                    self.run = false;
+                } else {
+                    if self.current_tab >= self.tabs.len() {
+                        self.current_tab -= 1;
+                    }
+                }
+            }
+            Command::NewNote => {
+                let result = self.new_note();
                 self.handle_error(result);
             }
+            Command::FollowLink => {
+                let tab = &self.tabs[self.current_tab];
+                let (row, col) = tab.textarea().cursor();
+                let current_line = &tab.textarea().lines()[row];
+
+                let col = if col + 1 > current_line.len() {
+                    col
+                } else {
+                    col + 1
+                };
+
+                let line_split = current_line.split_at(col);
+
+                if line_split.0.contains("[[") && !line_split.0.contains("]]") {
+                    let bracket_start_idx = current_line.find("[[").unwrap() + 2;
+                    let bracket_end_idx = current_line.find("]]").unwrap();
+                    let inside_filename = &current_line[bracket_start_idx..bracket_end_idx];
+
+                    let filename = inside_filename.split("|").collect::<Vec<&str>>()[0].to_string();
+                    let result = self.follow_link(&filename);
+                    self.handle_error(result);
+                }
+            }
+            Command::InsertTemplate(Some(path)) => {
+                self.expand_template(path, None)?;
+            }
+            Command::InsertTemplate(None) => {
+                self.file_paths = get_all_filenames(true).unwrap();
+                if let Some(template) = self.pick_template()? {
+                    self.expand_template(template, None)?;
+                }
+            }
+            Command::NewFromTemplate(template) => {
+                self.file_paths = get_all_filenames(true).unwrap();
+                self.new_note_from_template(template)?;
+            }
+            Command::Palette => {
+                self.render_command_palette()?;
+            }
+            Command::ToggleTask => {
+                let tab = &mut self.tabs[self.current_tab];
+                let row = tab.textarea().cursor().0;
+                let mut lines = tab.textarea().lines().to_vec();
+                let (_, warning) = task::toggle(&mut lines, row, Local::now().date_naive())
+                    .map_err(VaultError::Command)?;
+                tab.set_lines(tab.current, &lines);
+                if let Some(warning) = warning {
+                    return Err(VaultError::Command(warning));
+                }
+            }
+            Command::OpenEmbed => {
+                self.open_embed()?;
+            }
+            Command::Fold(command) => {
+                self.tabs[self.current_tab].fold(command)?;
+            }
+            Command::Preview => {
+                self.render_preview()?;
+            }
+            Command::Tasks(query) => {
+                self.render_tasks(&query, false)?;
+            }
+            Command::Agenda(query) => {
+                self.render_tasks(&query, true)?;
+            }
+            Command::Replace(substitute) => {
+                self.replace_in_vault(substitute)?;
+            }
+            Command::Undo => self.tabs[self.current_tab].undo(),
+            Command::Redo => self.tabs[self.current_tab].redo(),
+            Command::UndoReplace => {
+                self.undo_replace()?;
+            }
+            Command::Set(options) if options.trim().is_empty() => {
+                self.render_notification_area(self.config.describe())?;
+            }
+            Command::Set(options) => {
+                for option in options.split_whitespace() {
+                    self.config.set(option)?;
+                }
+            }
+            Command::Date { phrase, link } => {
+                let now = Local::now();
+                let date = parse_natural(&phrase, now.date_naive())
+                    .ok_or_else(|| VaultError::Command(format!("Not a date: {}", phrase)))?;
+                let text = if link {
+                    let path = self.config.daily_note(date);
+                    format!("[[{}]]", path.with_extension("").display())
+                } else {
+                    // Keep the time of day, for formats with hours in them
+                    format_date(&(now + (date - now.date_naive())), &self.config.date_format)
+                };
+                let tab = &mut self.tabs[self.current_tab];
+                tab.textareas[tab.current].insert_str(text);
+            }
             Command::NewTab => {
                 self.tabs.push(Editor::new());
                 self.current_tab += 1;
             }
+            Command::SelectTab(tab) => {
+                if tab == 0 || tab > self.tabs.len() {
+                    return Err(VaultError::Command(format!("No tab {}", tab)));
+                }
+                self.current_tab = tab - 1;
+            }
             Command::FocusTab(tab) => {
                 let move_by: i32 = if tab == 0 { -1 } else { 1 };
 
@@ -823,7 +2237,7 @@ impl Vault<'_> {
                 self.file_paths = get_all_filenames(true).unwrap();
                 let inner_link = self.render_file_search()?;
 
-                if inner_link == "" {
+                if inner_link.is_empty() {
                     return Ok(());
                 }
 
@@ -832,11 +2246,155 @@ impl Vault<'_> {
                 let result = self.open_file(PathBuf::from(inner_link));
                 self.handle_error(result);
             }
+            Command::GoToLine
+            | Command::Delete
+            | Command::Callout { .. }
+            | Command::Table(_)
+            | Command::Substitute(_)
+            | Command::Global { .. } => {
+                self.exec_ex_command(ExCommand {
+                    range: None,
+                    bang: false,
+                    command,
+                })?;
+            }
             Command::None => (),
         }
 
         Ok(())
     }
+
+    /// Run a parsed command line, resolving its range against the current buffer
+    fn exec_ex_command(&mut self, ex_command: ExCommand) -> Result<(), VaultError> {
+        let marks = self.vim.visual_marks();
+        let tab = &mut self.tabs[self.current_tab];
+        let (cursor, _) = tab.textarea().cursor();
+        let len = tab.textarea().lines().len();
+        let resolve = |range: Option<Range>, default: Range| {
+            range.unwrap_or(default).resolve(cursor, len, marks)
+        };
+        let current_line = Range::current_line();
+
+        match ex_command.command {
+            Command::GoToLine => {
+                let (_, row) = resolve(ex_command.range, current_line)?;
+                let col = first_non_blank(&tab.textarea().lines()[row]);
+                jump(&mut tab.textareas[tab.current], (row, col));
+            }
+            Command::Delete => {
+                let rows = resolve(ex_command.range, current_line)?;
+                tab.delete_lines(rows);
+            }
+            Command::Callout { kind, title } => {
+                let (first, last) = resolve(ex_command.range, current_line)?;
+                let mut lines = tab.textarea().lines().to_vec();
+                for line in &mut lines[first..=last] {
+                    *line = if line.is_empty() {
+                        ">".to_string()
+                    } else {
+                        format!("> {}", line)
+                    };
+                }
+                let header = format!("> [!{}] {}", kind, title);
+                lines.insert(first, header.trim_end().to_string());
+                tab.set_lines(tab.current, &lines);
+                jump(&mut tab.textareas[tab.current], (first, 0));
+            }
+            Command::Table(edit) => {
+                let range = match ex_command.range {
+                    Some(range) => Some(resolve(Some(range), current_line)?),
+                    None => None,
+                };
+                let mut lines = tab.textarea().lines().to_vec();
+                let cursor = table::edit(&mut lines, tab.textarea().cursor(), range, &edit)
+                    .map_err(VaultError::Command)?;
+                tab.set_lines(tab.current, &lines);
+                jump(&mut tab.textareas[tab.current], cursor);
+            }
+            Command::Substitute(substitute) => {
+                let rows = resolve(ex_command.range, current_line)?;
+                let regex =
+                    substitute_regex(tab.textarea(), &substitute.pattern, substitute.ignore_case)?;
+                let (first, last) = rows;
+                let rows: Vec<usize> = (first..=last).collect();
+                let count = tab.substitute(&rows, &regex, &substitute.replacement, substitute.all);
+                if count == 0 {
+                    return Err(VaultError::Command(format!(
+                        "Pattern not found: {}",
+                        regex.as_str()
+                    )));
+                }
+            }
+            Command::Global { pattern, command } => {
+                let (first, last) = resolve(ex_command.range, Range::whole_file())?;
+                let regex = substitute_regex(tab.textarea(), &pattern, false)?;
+                let lines = tab.textarea().lines();
+                let rows: Vec<usize> = (first..=last)
+                    .filter(|&row| regex.is_match(&lines[row]) != ex_command.bang)
+                    .collect();
+                if rows.is_empty() {
+                    return Err(VaultError::Command(format!(
+                        "Pattern not found: {}",
+                        pattern
+                    )));
+                }
+
+                // Every line is done in one change, so a single undo brings them all back
+                match ExCommand::parse(&command)?.command {
+                    Command::GoToLine => {
+                        let row = *rows.last().unwrap();
+                        let col = first_non_blank(&lines[row]);
+                        jump(&mut tab.textareas[tab.current], (row, col));
+                    }
+                    Command::Delete => {
+                        let mut lines = lines.to_vec();
+                        for &row in rows.iter().rev() {
+                            lines.remove(row);
+                        }
+                        if lines.is_empty() {
+                            lines.push(String::new());
+                        }
+                        tab.set_lines(tab.current, &lines);
+                        // Like Vim, end up on the line after the last one deleted
+                        let row = (rows[rows.len() - 1] + 1 - rows.len()).min(lines.len() - 1);
+                        jump(&mut tab.textareas[tab.current], (row, 0));
+                    }
+                    Command::Substitute(substitute) => {
+                        // An empty pattern is the one of :global, as in Vim
+                        let inner = match substitute.pattern.as_str() {
+                            "" => &pattern,
+                            inner => inner,
+                        };
+                        let regex =
+                            substitute_regex(tab.textarea(), inner, substitute.ignore_case)?;
+                        // A line without a match for the inner :s is not an error for :g
+                        tab.substitute(&rows, &regex, &substitute.replacement, substitute.all);
+                    }
+                    _ => {
+                        return Err(VaultError::Command(
+                            "Only :d and :s can be run by :global".to_string(),
+                        ));
+                    }
+                }
+            }
+            command => self.exec_command(command)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// The path of the note a link names, written with or without its extension
+fn note_path(name: &str) -> PathBuf {
+    PathBuf::from(name.strip_suffix(".md").unwrap_or(name).to_string() + ".md")
+}
+
+/// Whether a file of the vault is a note: a Markdown file not in a hidden folder
+fn is_note(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "md")
+        && !path.components().any(|component| {
+            matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+        })
 }
 
 fn populate_filenames(current_path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
@@ -846,6 +2404,10 @@ fn populate_filenames(current_path: &Path, files: &mut Vec<PathBuf>) -> io::Resu
             let entry_path = entry.path();
 
             if entry_path.is_dir() {
+                // The vault's own state is not a note
+                if entry_path.file_name().is_some_and(|name| name == DATA_DIR) {
+                    continue;
+                }
                 populate_filenames(&entry_path, files)?;
             } else if entry_path.is_file() {
                 let path = 'block: {
@@ -871,7 +2433,7 @@ fn get_all_filenames(use_current_dir: bool) -> io::Result<Vec<PathBuf>> {
     let paths: Vec<String> = 'block: {
         let paths: Vec<String> = args.skip(1).map(|arg| arg.into_string().unwrap()).collect();
         // If no dir provided use current dir
-        if paths.len() == 0 || use_current_dir {
+        if paths.is_empty() || use_current_dir {
             break 'block vec![".".to_string()];
         }
         paths
@@ -884,82 +2446,151 @@ fn get_all_filenames(use_current_dir: bool) -> io::Result<Vec<PathBuf>> {
     Ok(all_files)
 }
 
-fn change_moment_syntax_to_chrono_syntax(moment: &str) -> &str {
-    match moment {
-        "M" => "%-m",
-        "MM" => "%m",
-        "MMM" => "%b",
-        "MMMM" => "%B",
-        "Y" => "%Y",
-        "DD" => "%d",
-        "D" => "%-d",
-        "H" => "%-H",
-        "HH" => "%H",
-        "m" => "%-M",
-        "mm" => "%M",
-        _ => "",
-    }
+/// Fill in the `{{date}}` and `{{time}}` variables of a string, which is left as it is
+/// when it is not a valid template
+pub fn get_formated_date(string: String) -> String {
+    Template::parse(&string)
+        .and_then(|template| template.expand(&Context::new("")))
+        .map(|(text, _)| text)
+        .unwrap_or(string)
 }
 
-fn get_date(date: &str) -> String {
-    let mut return_date = String::new();
-    let mut current_format = String::new();
-    let mut counter = 0;
-    for char in date.to_string().chars() {
-        counter += 1;
-        let char = char.to_string();
-
-        current_format += &char;
-
-        let matched = change_moment_syntax_to_chrono_syntax(&current_format);
-
-        if matched == "" {
-            current_format.pop();
-            return_date += change_moment_syntax_to_chrono_syntax(&current_format);
-            current_format.clear();
-            // This means there is no separator character between patterns
-            // Like YMMDD instead of Y-MM-DD
-            // So add it to current_format and do not add it to return_date
-            if change_moment_syntax_to_chrono_syntax(&char) != "" {
-                current_format += &char;
-                continue;
-            }
-
-            return_date += &char;
+/// Translate a search as typed to a regex. Like in Vim, `\V` makes the pattern literal,
+/// `\v` keeps the usual regex syntax, and `\c` or `\C` ignore or match case whatever
+/// the options say
+fn search_regex(pattern: &str, config: &Config) -> String {
+    let mut literal = false;
+    let mut ignore_case = None;
+    let mut rest = String::new();
+
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            rest.push(c);
+            continue;
         }
-
-        if counter == date.len() {
-            return_date += change_moment_syntax_to_chrono_syntax(&current_format);
+        match chars.next() {
+            Some('V') => literal = true,
+            Some('v') => literal = false,
+            Some('c') => ignore_case = Some(true),
+            Some('C') => ignore_case = Some(false),
+            Some(next) => {
+                rest.push('\\');
+                rest.push(next);
+            }
+            None => rest.push('\\'),
         }
     }
 
-    return_date
+    let ignore_case = ignore_case.unwrap_or_else(|| config.ignores_case(&rest));
+    let rest = if literal { regex::escape(&rest) } else { rest };
+    if ignore_case && !rest.is_empty() {
+        format!("(?i){}", rest)
+    } else {
+        rest
+    }
 }
 
-pub fn get_formated_date(string: String) -> String {
-    let mut new_string_list: Vec<String> = Vec::new();
+/// The regex of a `:s` or `:g` pattern, where an empty pattern is the last search
+fn substitute_regex(
+    textarea: &TextArea,
+    pattern: &str,
+    ignore_case: bool,
+) -> Result<Regex, VaultError> {
+    let pattern = match (pattern, textarea.search_pattern()) {
+        ("", Some(previous)) => previous.as_str(),
+        ("", None) => {
+            return Err(VaultError::Command(
+                "No previous regular expression".to_string(),
+            ));
+        }
+        (pattern, _) => pattern,
+    };
 
-    // TODO: Dates cannot have spaces as I split the string by spaces
-    for item in string.split(" ").map(|string| string.to_string()) {
-        if item.contains("{{") && item.contains("}}") {
-            let date_start = item.find("{{").unwrap();
-            let date_end = item.find("}}").unwrap();
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|error| VaultError::Command(format!("Invalid pattern: {}", error)))
+}
 
-            // 2 for '{{' and 5 for 'date:'
-            let date = &item[date_start + 7..date_end];
-            let date = get_date(date);
-            let date = Local::now().format(&date).to_string();
+/// Replace the text of a single line prompt
+fn set_line(textarea: &mut TextArea, line: &str) {
+    textarea.move_cursor(CursorMove::End);
+    textarea.delete_line_by_head();
+    textarea.insert_str(line);
+}
 
-            let item_start = &item[0..date_start];
-            // 2 for '}}
-            let item_end = &item[date_end + 2..item.len()];
+/// The completion popup under the start of the word at `at`, or over it when there is no
+/// room under it, and the area it takes
+fn completion_popup(
+    candidates: &[Candidate],
+    selected: usize,
+    at: Position,
+    area: Rect,
+) -> (Rect, Paragraph<'static>) {
+    const HEIGHT: usize = 8;
+    let label_width = candidates
+        .iter()
+        .map(|candidate| candidate.label.width())
+        .max()
+        .unwrap_or(0);
+    let source_width = candidates
+        .iter()
+        .map(|candidate| candidate.source.len())
+        .max()
+        .unwrap_or(0);
+
+    // The selected candidate stays in view
+    let first = selected.saturating_sub(HEIGHT - 1);
+    let lines: Vec<Line> = candidates
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(HEIGHT)
+        .map(|(index, candidate)| {
+            let style = if index == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            let padding = " ".repeat(label_width - candidate.label.width() + 1);
+            Line::from(vec![
+                Span::styled(format!("{}{}", candidate.label, padding), style),
+                Span::styled(
+                    format!("{:>source_width$}", candidate.source),
+                    style.fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+
+    let width = ((label_width + source_width + 3) as u16).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    // One column left of the word for the border
+    let x =
+        at.x.saturating_sub(1)
+            .min((area.x + area.width).saturating_sub(width));
+    let y = if at.y + 1 + height <= area.y + area.height {
+        at.y + 1
+    } else {
+        at.y.saturating_sub(height).max(area.y)
+    };
 
-            new_string_list.push(item_start.to_string() + &date + item_end);
-            continue;
-        }
+    let popup = Paragraph::new(lines).block(Block::bordered());
+    (Rect::new(x, y, width, height), popup)
+}
 
-        new_string_list.push(item);
+/// The Tab completion candidates shown under the command line, the picked one highlighted
+fn completion_line(candidates: &[String], selected: usize) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        let style = if index == selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(candidate.clone(), style));
+        spans.push(Span::raw(" "));
     }
-
-    new_string_list.join(" ")
+    Line::from(spans)
 }
@@ -0,0 +1,281 @@
+use std::ops::Range;
+
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Widget},
+};
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    fold::{self, Fold},
+    markdown::Highlighter,
+    vim::Mode,
+};
+
+/// What is highlighted as selected, depending on the visual mode
+enum Selection {
+    None,
+    /// Start is inclusive and end exclusive, like tui-textarea's own selection
+    Chars((usize, usize), (usize, usize)),
+    Lines(usize, usize),
+    Block {
+        rows: (usize, usize),
+        cols: (usize, usize),
+    },
+}
+
+impl Selection {
+    fn new(textarea: &TextArea, mode: Mode) -> Self {
+        let Some((start, end)) = textarea.selection_range() else {
+            return Selection::None;
+        };
+
+        match mode {
+            Mode::VisualLine => Selection::Lines(start.0, end.0),
+            Mode::VisualBlock => Selection::Block {
+                rows: (start.0, end.0),
+                cols: (start.1.min(end.1), start.1.max(end.1)),
+            },
+            _ => Selection::Chars(start, end),
+        }
+    }
+
+    fn contains(&self, row: usize, col: usize) -> bool {
+        match *self {
+            Selection::None => false,
+            Selection::Chars(start, end) => start <= (row, col) && (row, col) < end,
+            Selection::Lines(first, last) => first <= row && row <= last,
+            Selection::Block { rows, cols } => {
+                rows.0 <= row && row <= rows.1 && cols.0 <= col && col <= cols.1
+            }
+        }
+    }
+}
+
+/// Render the textarea of the current buffer with its Markdown highlighted, drawing the
+/// selection of every visual mode. The lines of closed folds are left out, and with
+/// `conceal` markup is hidden but on the cursor line. Returns where the cursor was drawn
+pub fn render_textarea(
+    frame: &mut Frame,
+    area: Rect,
+    textarea: &mut TextArea,
+    highlighter: &mut Highlighter,
+    closed: &[Fold],
+    mode: Mode,
+    conceal: bool,
+) -> Option<Position> {
+    let line_number_style = textarea.line_number_style();
+    let gutter = match line_number_style {
+        Some(_) => textarea.lines().len().to_string().len() as u16 + 2,
+        None => 0,
+    };
+    let [gutter_area, text_area] =
+        Layout::horizontal([Constraint::Length(gutter), Constraint::Min(1)]).areas(area);
+
+    // Let tui-textarea work out scrolling as usual, then draw the visible rows ourselves
+    textarea.remove_line_number();
+    textarea.render(text_area, &mut Buffer::empty(text_area));
+    if let Some(style) = line_number_style {
+        textarea.set_line_number_style(style);
+    }
+    let (top, left) = viewport(textarea);
+
+    let selection = Selection::new(textarea, mode);
+    let selection_style = textarea.selection_style();
+    // Closed folds leave room for the lines after them
+    let mut rows = Vec::new();
+    let mut row = top;
+    while rows.len() < text_area.height as usize && row < textarea.lines().len() {
+        match fold::hiding(closed, row) {
+            Some(fold) => row = fold.end + 1,
+            None => {
+                rows.push(row);
+                row += 1;
+            }
+        }
+    }
+    highlighter.update(textarea.lines(), row);
+
+    let (cursor_row, cursor_col) = textarea.cursor();
+    let cursor = rows.iter().position(|row| *row == cursor_row).map(|y| {
+        let line = &textarea.lines()[cursor_row];
+        let width = display_width(line, cursor_col, textarea.tab_length() as usize);
+        Position::new(
+            text_area.x + width.saturating_sub(left).min(text_area.width as usize) as u16,
+            text_area.y + y as u16,
+        )
+    });
+
+    let mut numbers = Vec::new();
+    let mut lines = Vec::new();
+    for row in rows {
+        numbers.push(Line::from(format!(
+            "{:>width$} ",
+            row + 1,
+            width = gutter.saturating_sub(1) as usize
+        )));
+        let hidden = if conceal && row != textarea.cursor().0 {
+            highlighter.hidden(row)
+        } else {
+            &[]
+        };
+        let mut line = line_spans(
+            textarea,
+            row,
+            highlighter.styles(row),
+            hidden,
+            &selection,
+            selection_style,
+        );
+        if let Some(fold) = closed.iter().find(|fold| fold.start == row) {
+            line.push_span(Span::styled(
+                format!(" ⋯ {} lines", fold.end - fold.start),
+                Style::new().fg(Color::DarkGray),
+            ));
+        }
+        lines.push(line);
+    }
+
+    if let Some(style) = line_number_style {
+        frame.render_widget(
+            Paragraph::new(Text::from(numbers)).style(style),
+            gutter_area,
+        );
+    }
+    frame.render_widget(
+        Paragraph::new(Text::from(lines))
+            .style(textarea.style())
+            .scroll((0, left as u16)),
+        text_area,
+    );
+    cursor
+}
+
+/// How many cells the first characters of a line take
+fn display_width(line: &str, chars: usize, tab_length: usize) -> usize {
+    line.chars().take(chars).fold(0, |width, c| match c {
+        '\t' if tab_length > 0 => width + tab_length - width % tab_length,
+        c => width + c.width().unwrap_or(0),
+    })
+}
+
+fn line_spans(
+    textarea: &TextArea,
+    row: usize,
+    markdown: &[Style],
+    hidden: &[Range<usize>],
+    selection: &Selection,
+    selection_style: Style,
+) -> Line<'static> {
+    let line = &textarea.lines()[row];
+    let chars: Vec<char> = line.chars().collect();
+    let (cursor_row, cursor_col) = textarea.cursor();
+
+    // One more style than characters for the cell after the end of the line
+    let mut styles = vec![Style::default(); chars.len() + 1];
+    styles[..markdown.len()].copy_from_slice(markdown);
+
+    if row == cursor_row {
+        for style in styles.iter_mut() {
+            *style = style.patch(textarea.cursor_line_style());
+        }
+    }
+
+    if let Some(pattern) = textarea.search_pattern() {
+        for found in pattern.find_iter(line) {
+            let start = line[..found.start()].chars().count();
+            let end = start + found.as_str().chars().count();
+            // The match the cursor is on stands out from the others
+            let search_style = if (row, start) == (cursor_row, cursor_col) {
+                textarea.search_style().add_modifier(Modifier::REVERSED)
+            } else {
+                textarea.search_style()
+            };
+            for style in &mut styles[start..end] {
+                *style = style.patch(search_style);
+            }
+        }
+    }
+
+    for (col, style) in styles.iter_mut().enumerate() {
+        if selection.contains(row, col) {
+            *style = style.patch(selection_style);
+        }
+    }
+
+    if row == cursor_row {
+        let col = cursor_col.min(chars.len());
+        styles[col] = styles[col].patch(textarea.cursor_style());
+    }
+
+    let tab_length = textarea.tab_length() as usize;
+    let mut spans: Vec<Span> = Vec::new();
+    let mut text = String::new();
+    let mut current = styles[0];
+    let mut width = 0;
+
+    for (col, style) in styles.iter().enumerate() {
+        if *style != current && !text.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut text), current));
+        }
+        current = *style;
+
+        if hidden.iter().any(|range| range.contains(&col)) {
+            continue;
+        }
+        match chars.get(col) {
+            Some('\t') if tab_length > 0 => {
+                let len = tab_length - width % tab_length;
+                text.extend(std::iter::repeat_n(' ', len));
+                width += len;
+            }
+            Some(c) => {
+                text.push(*c);
+                width += c.width().unwrap_or(0);
+            }
+            // Only draw the cell after the end of the line when something highlights it
+            None if *style != Style::default() => text.push(' '),
+            None => (),
+        }
+    }
+    if !text.is_empty() {
+        spans.push(Span::styled(text, current));
+    }
+
+    Line::from(spans)
+}
+
+/// The first row and column drawn by the last render. tui-textarea keeps its viewport
+/// private, but CursorMove::InViewport clamps the cursor to it, which is enough to find out
+/// what was drawn
+pub fn viewport(textarea: &mut TextArea) -> (usize, usize) {
+    let cursor = textarea.cursor();
+    textarea.move_cursor(CursorMove::Top);
+    textarea.move_cursor(CursorMove::InViewport);
+    let top = textarea.cursor().0;
+    // The cursor line is always wide enough to reach the first visible column
+    jump(textarea, (cursor.0, 0));
+    textarea.move_cursor(CursorMove::InViewport);
+    let left = textarea.cursor().1;
+    jump(textarea, cursor);
+    (top, left)
+}
+
+/// The last row drawn by the last render that holds a line
+pub fn viewport_bottom(textarea: &mut TextArea) -> usize {
+    let cursor = textarea.cursor();
+    textarea.move_cursor(CursorMove::Bottom);
+    textarea.move_cursor(CursorMove::InViewport);
+    let bottom = textarea.cursor().0;
+    jump(textarea, cursor);
+    bottom
+}
+
+pub fn jump(textarea: &mut TextArea, (row, col): (usize, usize)) {
+    textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+}
@@ -1,13 +1,31 @@
+use chrono::{Local, Months, NaiveDate, TimeDelta};
 use tui_textarea::{CursorMove, Input, Key, Scrolling, TextArea};
 
-use crate::command::Command;
+use crate::{
+    command::Command,
+    complete,
+    config::Config,
+    date::parse_natural,
+    fold::{self, FoldCommand},
+    list, pair, table,
+    view::{jump, viewport, viewport_bottom},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Insert,
     Normal,
     Visual,
+    VisualLine,
+    VisualBlock,
     Operator(char),
+    Replace,
+}
+
+impl Mode {
+    pub fn is_visual(self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine | Mode::VisualBlock)
+    }
 }
 
 pub enum Transition {
@@ -26,10 +44,47 @@ pub enum Search {
     Backward,
 }
 
+/// Text typed on the first line of a blockwise `I` or `A`, repeated on the other lines on leaving insert mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInsert {
+    first: usize,
+    last: usize,
+    col: usize,
+    /// Length of the first line before anything was typed
+    len: usize,
+    /// Pad lines too short to reach the column, as `A` does but `I` does not
+    pad: bool,
+}
+
+/// The last `f`, `F`, `t` or `T` motion, repeated by `;` and `,`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Find {
+    motion: char,
+    target: char,
+}
+
+impl Find {
+    fn reversed(self) -> Self {
+        let motion = match self.motion {
+            'f' => 'F',
+            'F' => 'f',
+            't' => 'T',
+            _ => 't',
+        };
+        Self { motion, ..self }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Vim {
     pub mode: Mode,
     pending: Input,
+    last_find: Option<Find>,
+    /// Characters overwritten in replace mode, restored by backspace
+    replaced: Vec<Option<char>>,
+    block_insert: Option<BlockInsert>,
+    /// First and last row of the last visual selection, for the `'<` and `'>` marks
+    visual_marks: Option<(usize, usize)>,
 }
 
 impl Vim {
@@ -37,6 +92,10 @@ impl Vim {
         Self {
             mode,
             pending: Input::default(),
+            last_find: None,
+            replaced: Vec::new(),
+            block_insert: None,
+            visual_marks: None,
         }
     }
 
@@ -44,17 +103,297 @@ impl Vim {
         Self {
             mode: self.mode,
             pending,
+            last_find: self.last_find,
+            replaced: Vec::new(),
+            block_insert: self.block_insert,
+            visual_marks: self.visual_marks,
+        }
+    }
+
+    /// Switch mode while keeping state that outlives a single mode, like the last find
+    pub fn with_mode(&self, mode: Mode) -> Self {
+        Self {
+            mode,
+            pending: Input::default(),
+            last_find: self.last_find,
+            replaced: Vec::new(),
+            block_insert: self.block_insert,
+            visual_marks: self.visual_marks,
+        }
+    }
+
+    pub fn visual_marks(&self) -> Option<(usize, usize)> {
+        self.visual_marks
+    }
+
+    fn pending_char(pending: &Input) -> Option<char> {
+        match pending {
+            Input {
+                key: Key::Char(c),
+                ctrl: false,
+                ..
+            } => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// Abort a motion that could not move, dropping a half-typed operator
+    fn cancel_operator(&self, textarea: &mut TextArea) -> Transition {
+        if matches!(self.mode, Mode::Operator(_)) {
+            textarea.cancel_selection();
+            return Transition::Mode(Mode::Normal);
         }
+        Transition::Nop
     }
 
-    pub fn exec(&self, input: Input, textarea: &mut TextArea) -> Transition {
+    /// Make the motion that just ran inclusive when it is the target of an operator
+    fn include_cursor(&self, textarea: &mut TextArea, origin: (usize, usize)) {
+        if !matches!(self.mode, Mode::Operator(_)) {
+            return;
+        }
+
+        let cursor = textarea.cursor();
+        if cursor >= origin {
+            textarea.move_cursor(CursorMove::Forward);
+        } else {
+            // Moving backwards, so the character under the start of the selection has to be included
+            textarea.cancel_selection();
+            jump(textarea, origin);
+            textarea.move_cursor(CursorMove::Forward);
+            textarea.start_selection();
+            jump(textarea, cursor);
+        }
+    }
+
+    /// `J` and `gJ`, joining the cursor line with the next one or every line of the selection
+    fn join(&self, textarea: &mut TextArea, spaces: bool) {
+        let (row, count) = match textarea.selection_range() {
+            Some(((start, _), (end, _))) if self.mode.is_visual() => (start, (end - start).max(1)),
+            _ => (textarea.cursor().0, 1),
+        };
+        textarea.cancel_selection();
+
+        for _ in 0..count {
+            let lines = textarea.lines();
+            if row + 1 >= lines.len() {
+                break;
+            }
+            let current = lines[row].clone();
+            let next = lines[row + 1].clone();
+            let trimmed = next.trim_start();
+
+            jump(textarea, (row, current.chars().count()));
+            if spaces {
+                textarea.delete_str(1 + next.chars().count() - trimmed.chars().count());
+                let space = !trimmed.is_empty()
+                    && !trimmed.starts_with(')')
+                    && !current.is_empty()
+                    && !current.ends_with(char::is_whitespace);
+                if space {
+                    textarea.insert_char(' ');
+                    textarea.move_cursor(CursorMove::Back);
+                }
+            } else {
+                textarea.delete_str(1);
+            }
+        }
+    }
+
+    /// Turn the selection of the current visual mode into the charwise selection tui-textarea works on
+    fn select_visual(&self, textarea: &mut TextArea) {
+        if self.mode != Mode::VisualLine {
+            textarea.move_cursor(CursorMove::Forward); // Vim's text selection is inclusive
+            return;
+        }
+
+        if let Some(((first, _), (last, _))) = textarea.selection_range() {
+            select_lines(textarea, first, last);
+        }
+    }
+
+    fn start_block_insert(
+        &mut self,
+        textarea: &mut TextArea,
+        first: usize,
+        last: usize,
+        col: usize,
+        pad: bool,
+    ) -> Transition {
+        textarea.cancel_selection();
+        pad_line(textarea, first, col);
+        jump(textarea, (first, col));
+        self.block_insert = Some(BlockInsert {
+            first,
+            last,
+            col,
+            len: textarea.lines()[first].chars().count(),
+            pad,
+        });
+        Transition::Mode(Mode::Insert)
+    }
+
+    pub fn exec(&mut self, input: Input, textarea: &mut TextArea, config: &Config) -> Transition {
         if input.key == Key::Null {
             return Transition::Nop;
         }
 
+        // A pending key is only ever consumed by the key right after it
+        let pending = std::mem::take(&mut self.pending);
+        let origin = textarea.cursor();
+
         match self.mode {
-            Mode::Normal | Mode::Visual | Mode::Operator(_) => {
+            Mode::Normal
+            | Mode::Visual
+            | Mode::VisualLine
+            | Mode::VisualBlock
+            | Mode::Operator(_) => {
                 match input {
+                    Input {
+                        key: Key::Char(target),
+                        ctrl: false,
+                        ..
+                    } if matches!(Self::pending_char(&pending), Some('f' | 'F' | 't' | 'T')) => {
+                        let find = Find {
+                            motion: Self::pending_char(&pending).unwrap(),
+                            target,
+                        };
+                        self.last_find = Some(find);
+                        if !find_char(textarea, find, false) {
+                            return self.cancel_operator(textarea);
+                        }
+                        if matches!(find.motion, 'f' | 't') {
+                            self.include_cursor(textarea, origin);
+                        }
+                    }
+                    Input {
+                        key: Key::Char(c @ (';' | ',')),
+                        ctrl: false,
+                        ..
+                    } => {
+                        let Some(find) = self.last_find else {
+                            return self.cancel_operator(textarea);
+                        };
+                        let find = if c == ',' { find.reversed() } else { find };
+                        if !find_char(textarea, find, true) {
+                            return self.cancel_operator(textarea);
+                        }
+                        if matches!(find.motion, 'f' | 't') {
+                            self.include_cursor(textarea, origin);
+                        }
+                    }
+                    Input {
+                        key: Key::Char(c @ ('a' | 'o' | 'c' | 'R' | 'M')),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('z') && self.mode == Mode::Normal => {
+                        return Transition::CommandExec(Command::Fold(match c {
+                            'a' => FoldCommand::Toggle,
+                            'o' => FoldCommand::Open,
+                            'c' => FoldCommand::Close,
+                            'R' => FoldCommand::OpenAll,
+                            _ => FoldCommand::CloseAll,
+                        }));
+                    }
+                    // Like Vim, zj goes to the start of the next fold and zk to the end of
+                    // the previous one
+                    Input {
+                        key: Key::Char(c @ ('j' | 'k')),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('z') => {
+                        let (row, _) = textarea.cursor();
+                        let folds = fold::folds(textarea.lines());
+                        let target = if c == 'j' {
+                            folds.iter().map(|fold| fold.start).find(|&start| start > row)
+                        } else {
+                            folds.iter().map(|fold| fold.end).filter(|&end| end < row).max()
+                        };
+                        let Some(target) = target else {
+                            return self.cancel_operator(textarea);
+                        };
+                        let col = first_non_blank(&textarea.lines()[target]);
+                        jump(textarea, (target, col));
+                    }
+                    Input {
+                        key: Key::Char(c @ ('z' | 't' | 'b')),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('z') => {
+                        let (row, _) = textarea.cursor();
+                        match c {
+                            't' => scroll_to_top(textarea, row),
+                            'z' => {
+                                scroll_to_top(textarea, row);
+                                textarea.scroll(Scrolling::HalfPageUp);
+                            }
+                            _ => {
+                                scroll_to_top(textarea, row + 1);
+                                textarea.scroll(Scrolling::PageUp);
+                            }
+                        }
+                        jump(textarea, origin);
+                        return Transition::Nop;
+                    }
+                    Input {
+                        key: Key::Char(c),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('r') => {
+                        if self.mode.is_visual() {
+                            self.select_visual(textarea);
+                            replace_selection(textarea, |text| {
+                                text.chars()
+                                    .map(|old| if old == '\n' { old } else { c })
+                                    .collect()
+                            });
+                            return Transition::Mode(Mode::Normal);
+                        }
+                        if origin.1 < textarea.lines()[origin.0].chars().count() {
+                            textarea.start_selection();
+                            textarea.move_cursor(CursorMove::Forward);
+                            textarea.insert_str(c.to_string());
+                            textarea.move_cursor(CursorMove::Back);
+                        }
+                        return self.cancel_operator(textarea);
+                    }
+                    Input {
+                        key: Key::Char(op @ ('u' | 'U' | '~')),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('g') && self.mode == Mode::Normal => {
+                        textarea.start_selection();
+                        return Transition::Mode(Mode::Operator(op));
+                    }
+                    Input {
+                        key: Key::Char('x'),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('g') && self.mode == Mode::Normal => {
+                        return Transition::CommandExec(Command::ToggleTask);
+                    }
+                    Input {
+                        key: Key::Char('J'),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('g') => {
+                        self.join(textarea, false);
+                        return self.cancel_operator(textarea);
+                    }
+                    Input {
+                        key: Key::Char('j'),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('g') => {
+                        // The editor never wraps lines, so a display line is a buffer line
+                        textarea.move_cursor(CursorMove::Down)
+                    }
+                    Input {
+                        key: Key::Char('k'),
+                        ctrl: false,
+                        ..
+                    } if Self::pending_char(&pending) == Some('g') => {
+                        textarea.move_cursor(CursorMove::Up)
+                    }
                     Input {
                         key: Key::Char('h'),
                         ..
@@ -91,13 +430,167 @@ impl Vim {
                         ..
                     } => textarea.move_cursor(CursorMove::WordBack),
                     Input {
-                        key: Key::Char('^'),
+                        key: Key::Char('0'),
                         ..
                     } => textarea.move_cursor(CursorMove::Head),
+                    Input {
+                        key: Key::Char('^'),
+                        ..
+                    } => {
+                        let (row, _) = textarea.cursor();
+                        jump(textarea, (row, first_non_blank(&textarea.lines()[row])));
+                    }
                     Input {
                         key: Key::Char('$'),
                         ..
                     } => textarea.move_cursor(CursorMove::End),
+                    Input {
+                        key: Key::Char('W'),
+                        ..
+                    } => {
+                        let (row, col) = big_word_forward(textarea.lines(), origin);
+                        if matches!(self.mode, Mode::Operator(_)) && row != origin.0 {
+                            // Like Vim, an operator never eats the line break after the last WORD
+                            textarea.move_cursor(CursorMove::End);
+                        } else {
+                            jump(textarea, (row, col));
+                        }
+                    }
+                    Input {
+                        key: Key::Char('B'),
+                        ..
+                    } => jump(textarea, big_word_back(textarea.lines(), origin)),
+                    Input {
+                        key: Key::Char('E'),
+                        ..
+                    } => {
+                        jump(textarea, big_word_end(textarea.lines(), origin));
+                        self.include_cursor(textarea, origin);
+                    }
+                    Input {
+                        key: Key::Char('}'),
+                        ..
+                    } => jump(textarea, paragraph_forward(textarea.lines(), origin.0)),
+                    Input {
+                        key: Key::Char('{'),
+                        ..
+                    } => jump(textarea, paragraph_back(textarea.lines(), origin.0)),
+                    Input {
+                        key: Key::Char(c @ ('H' | 'M' | 'L')),
+                        ..
+                    } => {
+                        let (top, _) = viewport(textarea);
+                        let bottom = viewport_bottom(textarea);
+                        let row = match c {
+                            'H' => top,
+                            'M' => top + (bottom - top) / 2,
+                            _ => bottom,
+                        };
+                        jump(textarea, (row, first_non_blank(&textarea.lines()[row])));
+                    }
+                    Input {
+                        key: Key::Char('%'),
+                        ..
+                    } => {
+                        let Some(pos) = matching_bracket(textarea.lines(), origin) else {
+                            return self.cancel_operator(textarea);
+                        };
+                        jump(textarea, pos);
+                        self.include_cursor(textarea, origin);
+                    }
+                    Input {
+                        key: Key::Char(c @ ('*' | '#')),
+                        ..
+                    } => {
+                        let Some((start, word)) = word_under_cursor(&textarea.lines()[origin.0], origin.1)
+                        else {
+                            return self.cancel_operator(textarea);
+                        };
+                        let pattern = format!(r"\b{}\b", regex::escape(&word));
+                        if textarea.set_search_pattern(pattern).is_err() {
+                            return self.cancel_operator(textarea);
+                        }
+                        jump(textarea, (origin.0, start));
+                        let found = if c == '*' {
+                            textarea.search_forward(false)
+                        } else {
+                            textarea.search_back(false)
+                        };
+                        if !found {
+                            jump(textarea, origin);
+                        }
+                    }
+                    Input {
+                        key: Key::Char(c @ ('I' | 'A')),
+                        ctrl: false,
+                        ..
+                    } if self.mode == Mode::VisualBlock => {
+                        let (rows, cols) = block_range(textarea);
+                        let (first, last) = (*rows.start(), *rows.end());
+                        if c == 'I' {
+                            return self.start_block_insert(textarea, first, last, cols.0, false);
+                        }
+                        // `$` leaves the cursor after the end of the line, which is not part of the block
+                        let widest = rows
+                            .map(|row| textarea.lines()[row].chars().count())
+                            .max()
+                            .unwrap_or(0);
+                        let col = cols.1.min(widest.saturating_sub(1)) + 1;
+                        return self.start_block_insert(textarea, first, last, col, true);
+                    }
+                    Input {
+                        key: Key::Char('R'),
+                        ctrl: false,
+                        ..
+                    } if self.mode == Mode::Normal => return Transition::Mode(Mode::Replace),
+                    Input {
+                        key: Key::Char('~'),
+                        ctrl: false,
+                        ..
+                    } if self.mode == Mode::Normal => {
+                        if origin.1 < textarea.lines()[origin.0].chars().count() {
+                            textarea.start_selection();
+                            textarea.move_cursor(CursorMove::Forward);
+                            replace_selection(textarea, toggle_case);
+                            textarea.move_cursor(CursorMove::Forward);
+                        }
+                        return Transition::Nop;
+                    }
+                    Input {
+                        key: Key::Char(op @ ('u' | 'U' | '~')),
+                        ctrl: false,
+                        ..
+                    } if self.mode.is_visual() => {
+                        self.select_visual(textarea);
+                        replace_selection(textarea, case_operator(op));
+                        return Transition::Mode(Mode::Normal);
+                    }
+                    Input {
+                        key: Key::Char(op @ ('>' | '<')),
+                        ctrl: false,
+                        ..
+                    } if self.mode.is_visual() => {
+                        let ((start, _), (end, _)) = textarea.selection_range().unwrap();
+                        textarea.cancel_selection();
+                        shift_lines(textarea, start..=end, op == '>');
+                        return Transition::Mode(Mode::Normal);
+                    }
+                    Input {
+                        key: Key::Char('J'),
+                        ctrl: false,
+                        ..
+                    } if self.mode != Mode::Insert => {
+                        self.join(textarea, true);
+                        return self.cancel_operator(textarea);
+                    }
+                    Input {
+                        key: Key::Char(c @ ('a' | 'x')),
+                        ctrl: true,
+                        ..
+                    } if self.mode == Mode::Normal => {
+                        increment(textarea, if c == 'a' { 1 } else { -1 });
+                        return Transition::Nop;
+                    }
                     Input {
                         key: Key::Char('D'),
                         ..
@@ -113,6 +606,20 @@ impl Vim {
                         textarea.cancel_selection();
                         return Transition::Mode(Mode::Insert);
                     }
+                    Input {
+                        key: Key::Char('p'),
+                        ctrl: true,
+                        ..
+                    } if self.mode == Mode::Normal => {
+                        return Transition::CommandExec(Command::Palette);
+                    }
+                    Input {
+                        key: Key::Char(' '),
+                        ctrl: true,
+                        ..
+                    } if self.mode == Mode::Normal => {
+                        return Transition::CommandExec(Command::ToggleTask);
+                    }
                     Input {
                         key: Key::Char('p'),
                         ..
@@ -124,17 +631,15 @@ impl Vim {
                         key: Key::Char('u'),
                         ctrl: false,
                         ..
-                    } => {
-                        textarea.undo();
-                        return Transition::Mode(Mode::Normal);
+                    } if !matches!(self.mode, Mode::Operator(_)) => {
+                        return Transition::CommandExec(Command::Undo);
                     }
                     Input {
                         key: Key::Char('r'),
                         ctrl: true,
                         ..
                     } => {
-                        textarea.redo();
-                        return Transition::Mode(Mode::Normal);
+                        return Transition::CommandExec(Command::Redo);
                     }
                     Input {
                         key: Key::Char('x'),
@@ -191,6 +696,13 @@ impl Vim {
                         textarea.move_cursor(CursorMove::Head);
                         return Transition::Mode(Mode::Insert);
                     }
+                    Input {
+                        key: Key::Char('e'),
+                        ctrl: true,
+                        ..
+                    } if self.mode == Mode::Normal => {
+                        return Transition::CommandExec(Command::Preview);
+                    }
                     Input {
                         key: Key::Char('e'),
                         ctrl: true,
@@ -200,7 +712,7 @@ impl Vim {
                         key: Key::Char('n'),
                         ctrl: true,
                         ..
-                    } => return Transition::AutoComplete,
+                    } => textarea.move_cursor(CursorMove::Down),
                     Input {
                         key: Key::Char('d'),
                         ctrl: true,
@@ -231,29 +743,26 @@ impl Vim {
                         return Transition::Search(Search::Backward);
                     }
                     Input {
-                        key: Key::Char('v'),
-                        ctrl: false,
-                        ..
-                    } if self.mode == Mode::Normal => {
-                        textarea.start_selection();
-                        return Transition::Mode(Mode::Visual);
-                    }
-                    Input {
-                        key: Key::Char('V'),
-                        ctrl: false,
+                        key: Key::Char(c @ ('v' | 'V')),
+                        ctrl,
                         ..
-                    } if self.mode == Mode::Normal => {
-                        textarea.move_cursor(CursorMove::Head);
-                        textarea.start_selection();
-                        textarea.move_cursor(CursorMove::End);
-                        return Transition::Mode(Mode::Visual);
+                    } if matches!(self.mode, Mode::Normal) || self.mode.is_visual() => {
+                        let mode = match (c, ctrl) {
+                            ('v', true) => Mode::VisualBlock,
+                            ('v', false) => Mode::Visual,
+                            _ => Mode::VisualLine,
+                        };
+                        // Typing the key of the current visual mode again leaves it, any other switches to it
+                        if self.mode == mode {
+                            textarea.cancel_selection();
+                            return Transition::Mode(Mode::Normal);
+                        }
+                        if self.mode == Mode::Normal {
+                            textarea.start_selection();
+                        }
+                        return Transition::Mode(mode);
                     }
-                    Input { key: Key::Esc, .. }
-                    | Input {
-                        key: Key::Char('v'),
-                        ctrl: false,
-                        ..
-                    } if self.mode == Mode::Visual => {
+                    Input { key: Key::Esc, .. } if self.mode.is_visual() => {
                         textarea.cancel_selection();
                         return Transition::Mode(Mode::Normal);
                     }
@@ -261,15 +770,7 @@ impl Vim {
                         key: Key::Char('g'),
                         ctrl: false,
                         ..
-                    } if matches!(
-                        self.pending,
-                        Input {
-                            key: Key::Char('g'),
-                            ctrl: false,
-                            ..
-                        }
-                    ) =>
-                    {
+                    } if Self::pending_char(&pending) == Some('g') => {
                         textarea.move_cursor(CursorMove::Top)
                     }
                     Input {
@@ -292,7 +793,7 @@ impl Vim {
                         }
                     }
                     Input {
-                        key: Key::Char(op @ ('y' | 'd' | 'c')),
+                        key: Key::Char(op @ ('y' | 'd' | 'c' | '>' | '<')),
                         ctrl: false,
                         ..
                     } if self.mode == Mode::Normal => {
@@ -303,8 +804,37 @@ impl Vim {
                         key: Key::Char('y'),
                         ctrl: false,
                         ..
-                    } if self.mode == Mode::Visual => {
-                        textarea.move_cursor(CursorMove::Forward); // Vim's text selection is inclusive
+                    } if self.mode == Mode::VisualBlock => {
+                        let (rows, cols) = block_range(textarea);
+                        let first = *rows.start();
+                        let text = rows
+                            .map(|row| block_segment(&textarea.lines()[row], cols))
+                            .collect::<Vec<String>>()
+                            .join("\n");
+                        textarea.cancel_selection();
+                        textarea.set_yank_text(text);
+                        jump(textarea, (first, cols.0));
+                        return Transition::Mode(Mode::Normal);
+                    }
+                    Input {
+                        key: Key::Char(op @ ('d' | 'c')),
+                        ctrl: false,
+                        ..
+                    } if self.mode == Mode::VisualBlock => {
+                        let (rows, cols) = block_range(textarea);
+                        let (first, last) = (*rows.start(), *rows.end());
+                        replace_block(textarea, rows, cols, |_| String::new());
+                        if op == 'd' {
+                            return Transition::Mode(Mode::Normal);
+                        }
+                        return self.start_block_insert(textarea, first, last, cols.0, false);
+                    }
+                    Input {
+                        key: Key::Char('y'),
+                        ctrl: false,
+                        ..
+                    } if self.mode.is_visual() => {
+                        self.select_visual(textarea);
                         textarea.copy();
                         return Transition::Mode(Mode::Normal);
                     }
@@ -312,8 +842,8 @@ impl Vim {
                         key: Key::Char('d'),
                         ctrl: false,
                         ..
-                    } if self.mode == Mode::Visual => {
-                        textarea.move_cursor(CursorMove::Forward); // Vim's text selection is inclusive
+                    } if self.mode.is_visual() => {
+                        self.select_visual(textarea);
                         textarea.cut();
                         return Transition::Mode(Mode::Normal);
                     }
@@ -321,8 +851,17 @@ impl Vim {
                         key: Key::Char('c'),
                         ctrl: false,
                         ..
-                    } if self.mode == Mode::Visual => {
-                        textarea.move_cursor(CursorMove::Forward); // Vim's text selection is inclusive
+                    } if self.mode.is_visual() => {
+                        if self.mode == Mode::VisualLine {
+                            // The lines are replaced by a single empty one
+                            let ((first, _), (last, _)) = textarea.selection_range().unwrap();
+                            textarea.cancel_selection();
+                            jump(textarea, (first, 0));
+                            textarea.start_selection();
+                            jump(textarea, (last, usize::MAX));
+                        } else {
+                            self.select_visual(textarea);
+                        }
                         textarea.cut();
                         return Transition::Mode(Mode::Insert);
                     }
@@ -331,6 +870,15 @@ impl Vim {
                         ..
                         // Do not wait until next key press, return Transition directly
                     } if self.mode == Mode::Normal => return Transition::CommandMode,
+                    Input {
+                        key: Key::Char(':'),
+                        ..
+                    } if self.mode.is_visual() => {
+                        let ((first, _), (last, _)) = textarea.selection_range().unwrap();
+                        self.visual_marks = Some((first, last));
+                        textarea.cancel_selection();
+                        return Transition::CommandMode;
+                    }
                     Input { key: Key::Enter, .. } if self.mode == Mode::Normal => {
                         return Transition::CommandExec(Command::FollowLink);
                     }
@@ -367,21 +915,148 @@ impl Vim {
                         textarea.cut();
                         Transition::Mode(Mode::Insert)
                     }
+                    Mode::Operator(op @ ('u' | 'U' | '~')) => {
+                        replace_selection(textarea, case_operator(op));
+                        Transition::Mode(Mode::Normal)
+                    }
+                    Mode::Operator(op @ ('>' | '<')) => {
+                        let ((start, _), (end, col)) = textarea.selection_range().unwrap();
+                        // A linewise selection ends at the head of the line after the last one
+                        let end = if col == 0 && end > start {
+                            end - 1
+                        } else {
+                            end
+                        };
+                        textarea.cancel_selection();
+                        shift_lines(textarea, start..=end, op == '>');
+                        Transition::Mode(Mode::Normal)
+                    }
                     _ => Transition::Nop,
                 }
             }
-            Mode::Insert => match input {
+            Mode::Replace => match input {
                 Input { key: Key::Esc, .. } => Transition::Mode(Mode::Normal),
+                Input {
+                    key: Key::Backspace,
+                    ..
+                } => {
+                    match self.replaced.pop() {
+                        Some(Some(old)) => {
+                            textarea.move_cursor(CursorMove::Back);
+                            textarea.delete_next_char();
+                            textarea.insert_char(old);
+                            textarea.move_cursor(CursorMove::Back);
+                        }
+                        Some(None) => {
+                            textarea.delete_char();
+                        }
+                        None => textarea.move_cursor(CursorMove::Back),
+                    }
+                    Transition::Mode(Mode::Replace)
+                }
+                // A line break is inserted rather than replacing, and backspace joins it back
+                Input {
+                    key: Key::Enter, ..
+                } => {
+                    textarea.insert_newline();
+                    self.replaced.push(None);
+                    Transition::Mode(Mode::Replace)
+                }
+                Input {
+                    key: Key::Char(_) | Key::Tab,
+                    ctrl: false,
+                    ..
+                } => {
+                    let char = match input.key {
+                        Key::Char(char) => char,
+                        _ => '\t',
+                    };
+                    let (row, col) = textarea.cursor();
+                    let old = textarea.lines()[row].chars().nth(col);
+                    if old.is_some() {
+                        textarea.delete_next_char();
+                    }
+                    textarea.insert_char(char);
+                    self.replaced.push(old);
+                    Transition::Mode(Mode::Replace)
+                }
+                // Like Vim, moving elsewhere leaves what was replaced so far as it is
+                input => {
+                    textarea.input(input);
+                    self.replaced.clear();
+                    Transition::Mode(Mode::Replace)
+                }
+            },
+            Mode::Insert => match input {
+                Input { key: Key::Esc, .. } => {
+                    if let Some(block) = self.block_insert.take() {
+                        finish_block_insert(textarea, block);
+                    }
+                    table::align(textarea);
+                    list::renumber(textarea);
+                    Transition::Mode(Mode::Normal)
+                }
                 Input {
                     key: Key::Char('n'),
                     ctrl: true,
                     ..
-                } => return Transition::AutoComplete,
+                } => Transition::AutoComplete,
+                Input {
+                    key: Key::Tab,
+                    ctrl: false,
+                    alt: false,
+                    shift: false,
+                } => {
+                    let (row, col) = textarea.cursor();
+                    let Some((start, phrase)) = date_trigger(&textarea.lines()[row], col) else {
+                        if !table::next_cell(textarea, true) && !list::indent(textarea, true) {
+                            textarea.input(input);
+                        }
+                        return Transition::Mode(Mode::Insert);
+                    };
+                    for _ in start..col {
+                        textarea.delete_char();
+                    }
+                    Transition::CommandExec(Command::Date { phrase, link: true })
+                }
+                Input {
+                    key: Key::Tab,
+                    shift: true,
+                    ..
+                } => {
+                    if !table::next_cell(textarea, false) {
+                        list::indent(textarea, false);
+                    }
+                    Transition::Mode(Mode::Insert)
+                }
+                Input {
+                    key: Key::Enter,
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } if self.block_insert.is_none() => {
+                    if !list::new_line(textarea) {
+                        textarea.insert_newline();
+                    }
+                    Transition::Mode(Mode::Insert)
+                }
+                Input {
+                    key: Key::Backspace,
+                    ..
+                } if config.autopair && pair::delete(textarea) => Transition::Mode(Mode::Insert),
                 Input {
                     key: Key::Char(char),
                     ..
                 } => {
-                    textarea.insert_char(char);
+                    if config.autopair {
+                        pair::insert(textarea, char);
+                    } else {
+                        textarea.insert_char(char);
+                    }
+                    let (row, col) = textarea.cursor();
+                    if complete::opens(&textarea.lines()[row], col) {
+                        return Transition::AutoComplete;
+                    }
                     Transition::Mode(Mode::Insert)
                 }
                 input => {
@@ -392,3 +1067,470 @@ impl Vim {
         }
     }
 }
+
+/// The column of the `@` of a date typed as `@next friday` right before the cursor,
+/// and the phrase after it
+fn date_trigger(line: &str, col: usize) -> Option<(usize, String)> {
+    let before: String = line.chars().take(col).collect();
+    let at = before.rfind('@')?;
+    // Leave addresses like name@example.com alone
+    if before[..at]
+        .chars()
+        .next_back()
+        .is_some_and(|c| !c.is_whitespace())
+    {
+        return None;
+    }
+
+    let phrase = &before[at + 1..];
+    parse_natural(phrase, Local::now().date_naive())?;
+    Some((before[..at].chars().count(), phrase.to_string()))
+}
+
+pub fn first_non_blank(line: &str) -> usize {
+    line.chars().position(|c| !c.is_whitespace()).unwrap_or(0)
+}
+
+/// The character at a position, or None at the end of a line
+fn char_at(lines: &[String], (row, col): (usize, usize)) -> Option<char> {
+    lines[row].chars().nth(col)
+}
+
+fn next_pos(lines: &[String], (row, col): (usize, usize)) -> Option<(usize, usize)> {
+    if col + 1 < lines[row].chars().count() {
+        Some((row, col + 1))
+    } else if row + 1 < lines.len() {
+        Some((row + 1, 0))
+    } else {
+        None
+    }
+}
+
+fn prev_pos(lines: &[String], (row, col): (usize, usize)) -> Option<(usize, usize)> {
+    if col > 0 {
+        Some((row, col.min(lines[row].chars().count()).saturating_sub(1)))
+    } else if row > 0 {
+        Some((row - 1, lines[row - 1].chars().count().saturating_sub(1)))
+    } else {
+        None
+    }
+}
+
+fn is_blank(c: Option<char>) -> bool {
+    c.is_none_or(char::is_whitespace)
+}
+
+fn find_char(textarea: &mut TextArea, find: Find, repeat: bool) -> bool {
+    let (row, col) = textarea.cursor();
+    let chars: Vec<char> = textarea.lines()[row].chars().collect();
+    // Repeating a `t` would otherwise stop right in front of the same character again
+    let skip = if repeat && matches!(find.motion, 't' | 'T') {
+        1
+    } else {
+        0
+    };
+
+    let col = match find.motion {
+        'f' | 't' => (col + 1 + skip..chars.len())
+            .find(|&i| chars[i] == find.target)
+            .map(|i| if find.motion == 't' { i - 1 } else { i }),
+        _ => (0..col.saturating_sub(skip))
+            .rev()
+            .find(|&i| chars[i] == find.target)
+            .map(|i| if find.motion == 'T' { i + 1 } else { i }),
+    };
+
+    match col {
+        Some(col) => {
+            jump(textarea, (row, col));
+            true
+        }
+        None => false,
+    }
+}
+
+fn big_word_forward(lines: &[String], (row, col): (usize, usize)) -> (usize, usize) {
+    let chars: Vec<char> = lines[row].chars().collect();
+    let mut col = col;
+    while col < chars.len() && !chars[col].is_whitespace() {
+        col += 1;
+    }
+    while col < chars.len() && chars[col].is_whitespace() {
+        col += 1;
+    }
+    if col < chars.len() {
+        return (row, col);
+    }
+
+    for (row, line) in lines.iter().enumerate().skip(row + 1) {
+        // An empty line counts as a WORD of its own
+        if line.is_empty() {
+            return (row, 0);
+        }
+        if let Some(col) = line.chars().position(|c| !c.is_whitespace()) {
+            return (row, col);
+        }
+    }
+
+    let row = lines.len() - 1;
+    (row, lines[row].chars().count())
+}
+
+fn big_word_end(lines: &[String], pos: (usize, usize)) -> (usize, usize) {
+    let Some(mut pos) = next_pos(lines, pos) else {
+        return pos;
+    };
+    while is_blank(char_at(lines, pos)) {
+        match next_pos(lines, pos) {
+            Some(next) => pos = next,
+            None => return pos,
+        }
+    }
+    while let Some(next) = next_pos(lines, pos) {
+        if next.0 != pos.0 || is_blank(char_at(lines, next)) {
+            break;
+        }
+        pos = next;
+    }
+    pos
+}
+
+fn big_word_back(lines: &[String], pos: (usize, usize)) -> (usize, usize) {
+    let Some(mut pos) = prev_pos(lines, pos) else {
+        return pos;
+    };
+    while is_blank(char_at(lines, pos)) && !lines[pos.0].is_empty() {
+        match prev_pos(lines, pos) {
+            Some(prev) => pos = prev,
+            None => return pos,
+        }
+    }
+    while let Some(prev) = prev_pos(lines, pos) {
+        if prev.0 != pos.0 || is_blank(char_at(lines, prev)) {
+            break;
+        }
+        pos = prev;
+    }
+    pos
+}
+
+/// Vim's `}`, which stops on the empty line after a paragraph unlike CursorMove::ParagraphForward
+fn paragraph_forward(lines: &[String], row: usize) -> (usize, usize) {
+    let mut row = row;
+    while row < lines.len() && lines[row].is_empty() {
+        row += 1;
+    }
+    while row < lines.len() && !lines[row].is_empty() {
+        row += 1;
+    }
+    if row >= lines.len() {
+        let row = lines.len() - 1;
+        return (row, lines[row].chars().count());
+    }
+    (row, 0)
+}
+
+fn paragraph_back(lines: &[String], row: usize) -> (usize, usize) {
+    let mut row = row;
+    while row > 0 && lines[row].is_empty() {
+        row -= 1;
+    }
+    while row > 0 && !lines[row].is_empty() {
+        row -= 1;
+    }
+    (row, 0)
+}
+
+fn matching_bracket(lines: &[String], (row, col): (usize, usize)) -> Option<(usize, usize)> {
+    let chars: Vec<char> = lines[row].chars().collect();
+    let start = (col..chars.len()).find(|&i| "()[]{}".contains(chars[i]))?;
+    let open = chars[start];
+    let (close, forward) = match open {
+        '(' => (')', true),
+        '[' => (']', true),
+        '{' => ('}', true),
+        ')' => ('(', false),
+        ']' => ('[', false),
+        _ => ('{', false),
+    };
+
+    let mut depth = 0;
+    let mut pos = (row, start);
+    loop {
+        match char_at(lines, pos) {
+            Some(c) if c == open => depth += 1,
+            Some(c) if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            _ => (),
+        }
+        pos = if forward {
+            next_pos(lines, pos)?
+        } else {
+            prev_pos(lines, pos)?
+        };
+    }
+}
+
+/// The keyword under or after the cursor, as Vim's `*` and `#` pick it
+fn word_under_cursor(line: &str, col: usize) -> Option<(usize, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut start = (col..chars.len()).find(|&i| is_word(chars[i]))?;
+    while start > 0 && is_word(chars[start - 1]) {
+        start -= 1;
+    }
+    let end = (start..chars.len())
+        .find(|&i| !is_word(chars[i]))
+        .unwrap_or(chars.len());
+
+    Some((start, chars[start..end].iter().collect()))
+}
+
+/// Scroll so that `row` is the first row of the viewport
+fn scroll_to_top(textarea: &mut TextArea, row: usize) {
+    let (top, _) = viewport(textarea);
+    // Scrolling takes an i16, which long notes do not fit in
+    let mut delta = row as isize - top as isize;
+    while delta != 0 {
+        let step = delta.clamp(i16::MIN as isize, i16::MAX as isize);
+        textarea.scroll((step as i16, 0));
+        delta -= step;
+    }
+}
+
+/// Replace the selected text with `f` applied to it, leaving the cursor at the start of it
+fn replace_selection(textarea: &mut TextArea, f: impl Fn(&str) -> String) {
+    let Some((start, end)) = textarea.selection_range() else {
+        return;
+    };
+    let lines = textarea.lines();
+
+    let mut text = String::new();
+    for (row, line) in lines.iter().enumerate().take(end.0 + 1).skip(start.0) {
+        let from = if row == start.0 { start.1 } else { 0 };
+        let to = if row == end.0 { end.1 } else { usize::MAX };
+        text.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+        if row != end.0 {
+            text.push('\n');
+        }
+    }
+
+    textarea.insert_str(f(&text));
+    jump(textarea, start);
+}
+
+fn toggle_case(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| {
+            if c.is_uppercase() {
+                c.to_lowercase().collect::<Vec<char>>()
+            } else {
+                c.to_uppercase().collect()
+            }
+        })
+        .collect()
+}
+
+fn case_operator(op: char) -> fn(&str) -> String {
+    match op {
+        'u' => str::to_lowercase,
+        'U' => str::to_uppercase,
+        _ => toggle_case,
+    }
+}
+
+/// Indent or outdent whole lines by one level, the way `>>` and `<<` nest list items
+fn shift_lines(textarea: &mut TextArea, rows: std::ops::RangeInclusive<usize>, indent: bool) {
+    let unit = textarea.indent();
+    let first = *rows.start();
+
+    for row in rows {
+        let line = textarea.lines()[row].clone();
+        if line.is_empty() {
+            continue;
+        }
+
+        jump(textarea, (row, 0));
+        if indent {
+            textarea.insert_str(unit);
+        } else {
+            let width = if line.starts_with('\t') {
+                1
+            } else {
+                line.chars()
+                    .take(unit.len())
+                    .take_while(|c| *c == ' ')
+                    .count()
+            };
+            textarea.delete_str(width);
+        }
+    }
+
+    jump(textarea, (first, first_non_blank(&textarea.lines()[first])));
+}
+
+fn replace_range(textarea: &mut TextArea, row: usize, start: usize, end: usize, text: &str) {
+    textarea.cancel_selection();
+    jump(textarea, (row, start));
+    textarea.start_selection();
+    jump(textarea, (row, end));
+    textarea.insert_str(text);
+}
+
+/// Ctrl-a and Ctrl-x. A `YYYY-MM-DD` date under the cursor is stepped by the part the cursor is on,
+/// otherwise the number under or after the cursor is
+fn increment(textarea: &mut TextArea, delta: i64) {
+    let (row, col) = textarea.cursor();
+    let line = textarea.lines()[row].clone();
+    // `$` leaves the cursor after the last character
+    let col = col.min(line.chars().count().saturating_sub(1));
+
+    let date_regex = regex::Regex::new(r"\d{4}-\d{2}-\d{2}").unwrap();
+    for found in date_regex.find_iter(&line) {
+        let start = line[..found.start()].chars().count();
+        let end = start + 10;
+        if col < start || col >= end {
+            continue;
+        }
+        let Ok(date) = NaiveDate::parse_from_str(found.as_str(), "%Y-%m-%d") else {
+            break;
+        };
+
+        let months = Months::new(if col - start < 4 { 12 } else { 1 });
+        let date = if col - start >= 8 {
+            date.checked_add_signed(TimeDelta::days(delta))
+        } else if delta > 0 {
+            date.checked_add_months(months)
+        } else {
+            date.checked_sub_months(months)
+        };
+
+        if let Some(date) = date {
+            replace_range(
+                textarea,
+                row,
+                start,
+                end,
+                &date.format("%Y-%m-%d").to_string(),
+            );
+            jump(textarea, (row, col));
+        }
+        return;
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    let Some(mut start) = (col..chars.len()).find(|&i| chars[i].is_ascii_digit()) else {
+        return;
+    };
+    while start > 0 && chars[start - 1].is_ascii_digit() {
+        start -= 1;
+    }
+    if start > 0 && chars[start - 1] == '-' {
+        start -= 1;
+    }
+    let end = (start + 1..chars.len())
+        .find(|&i| !chars[i].is_ascii_digit())
+        .unwrap_or(chars.len());
+
+    let number: String = chars[start..end].iter().collect();
+    let Some(number) = number
+        .parse::<i64>()
+        .ok()
+        .and_then(|n| n.checked_add(delta))
+    else {
+        return;
+    };
+
+    let number = number.to_string();
+    replace_range(textarea, row, start, end, &number);
+    jump(textarea, (row, start + number.len() - 1));
+}
+
+/// Select whole lines the way deleting them should remove them, line breaks included
+pub fn select_lines(textarea: &mut TextArea, first: usize, last: usize) {
+    let len = textarea.lines().len();
+    textarea.cancel_selection();
+
+    if last + 1 < len {
+        jump(textarea, (first, 0));
+        textarea.start_selection();
+        jump(textarea, (last + 1, 0));
+    } else if first > 0 {
+        // Take the line break before the first line, as the last line has none after it
+        jump(textarea, (first - 1, usize::MAX));
+        textarea.start_selection();
+        jump(textarea, (last, usize::MAX));
+    } else {
+        jump(textarea, (first, 0));
+        textarea.start_selection();
+        jump(textarea, (last, usize::MAX));
+    }
+}
+
+/// Rows and inclusive columns of a blockwise selection
+fn block_range(textarea: &TextArea) -> (std::ops::RangeInclusive<usize>, (usize, usize)) {
+    let (start, end) = textarea.selection_range().unwrap_or_default();
+    (start.0..=end.0, (start.1.min(end.1), start.1.max(end.1)))
+}
+
+fn block_segment(line: &str, (first, last): (usize, usize)) -> String {
+    line.chars().skip(first).take(last + 1 - first).collect()
+}
+
+/// Replace the part of each line inside the block with `f` applied to it
+fn replace_block(
+    textarea: &mut TextArea,
+    rows: std::ops::RangeInclusive<usize>,
+    cols: (usize, usize),
+    f: impl Fn(&str) -> String,
+) {
+    let first = *rows.start();
+    textarea.cancel_selection();
+    for row in rows {
+        let len = textarea.lines()[row].chars().count();
+        if cols.0 >= len {
+            continue;
+        }
+        let segment = block_segment(&textarea.lines()[row], cols);
+        replace_range(textarea, row, cols.0, (cols.1 + 1).min(len), &f(&segment));
+    }
+    textarea.cancel_selection();
+    jump(textarea, (first, cols.0));
+}
+
+/// Pad a line with spaces until it reaches `col`
+fn pad_line(textarea: &mut TextArea, row: usize, col: usize) {
+    let len = textarea.lines()[row].chars().count();
+    if len < col {
+        jump(textarea, (row, len));
+        textarea.insert_str(" ".repeat(col - len));
+    }
+}
+
+fn finish_block_insert(textarea: &mut TextArea, block: BlockInsert) {
+    let (row, _) = textarea.cursor();
+    let line: Vec<char> = textarea.lines()[block.first].chars().collect();
+    // Like Vim, only text typed on the first line without breaking it is repeated
+    if row != block.first || line.len() <= block.len {
+        return;
+    }
+    let text: String = line[block.col..block.col + line.len() - block.len]
+        .iter()
+        .collect();
+
+    for row in block.first + 1..=block.last.min(textarea.lines().len() - 1) {
+        if block.pad {
+            pad_line(textarea, row, block.col);
+        } else if textarea.lines()[row].chars().count() < block.col {
+            continue;
+        }
+        jump(textarea, (row, block.col));
+        textarea.insert_str(&text);
+    }
+    jump(textarea, (block.first, block.col));
+}